@@ -0,0 +1,139 @@
+//! Two step admin handover shared by the `EmployerPool` contracts.
+//!
+//! The admin proposes a successor, who accepts once `delay` seconds have
+//! passed, and the admin can cancel the change until then. A new delay takes
+//! effect once the current delay has passed, so the admin cannot shorten it
+//! for a change they want to rush through, and it only applies to changes
+//! proposed after that. Other admin only methods compare the caller with
+//! [`Admin::get`] themselves.
+//!
+//! Contracts emit their own `AdminChanged`/`AdminChangeProposed`/
+//! `AdminChangeCancelled`/`AdminDelayChanged` events around these calls.
+
+use stylus_sdk::{
+    alloy_primitives::{Address, U64},
+    alloy_sol_types::sol,
+    prelude::*,
+};
+
+sol_storage! {
+    pub struct Admin {
+        address admin;
+        address pending_admin;
+        /// Time from which `pending_admin` can accept
+        uint64 change_eta;
+        uint64 delay;
+        /// Replaces `delay` from `delay_eta` on
+        uint64 pending_delay;
+        uint64 delay_eta;
+    }
+}
+
+sol! {
+    error AdminChangeNotReady(uint64 eta);
+}
+
+#[derive(SolidityError)]
+pub enum AdminError {
+    AdminChangeNotReady(AdminChangeNotReady),
+}
+
+impl Admin {
+    pub fn get(&self) -> Address {
+        self.admin.get()
+    }
+
+    /// Sets the first admin, returns false if there already is one
+    pub fn initialize(&mut self, admin: Address) -> bool {
+        if self.admin.get() != Address::ZERO {
+            return false;
+        }
+        self.admin.set(admin);
+        true
+    }
+
+    /// Proposes `new_admin`, returns the time from which it can accept
+    pub fn propose(&mut self, new_admin: Address) -> u64 {
+        assert_eq!(self.admin.get(), self.vm().msg_sender(), "Only admin can change admin");
+        assert_ne!(new_admin, Address::ZERO, "New admin cannot be the zero address");
+
+        let eta = self.vm().block_timestamp() + self.delay();
+        self.pending_admin.set(new_admin);
+        self.change_eta.set(U64::from(eta));
+        eta
+    }
+
+    /// Makes the calling pending admin the admin, returns the previous one
+    pub fn accept(&mut self) -> Result<Address, AdminError> {
+        let sender = self.vm().msg_sender();
+        let pending = self.pending_admin.get();
+        assert!(
+            pending != Address::ZERO && pending == sender,
+            "Only the pending admin can accept"
+        );
+        let eta = self.change_eta.get().to::<u64>();
+        if self.vm().block_timestamp() < eta {
+            return Err(AdminError::AdminChangeNotReady(AdminChangeNotReady { eta }));
+        }
+
+        let previous_admin = self.admin.get();
+        self.admin.set(sender);
+        self.clear_pending();
+        Ok(previous_admin)
+    }
+
+    /// Drops the proposed change, returns the admin that was pending
+    pub fn cancel(&mut self) -> Address {
+        assert_eq!(
+            self.admin.get(),
+            self.vm().msg_sender(),
+            "Only admin can cancel an admin change"
+        );
+        let pending_admin = self.pending_admin.get();
+        self.clear_pending();
+        pending_admin
+    }
+
+    /// Schedules `delay` to replace the current one, returns the time it takes effect
+    pub fn set_delay(&mut self, delay: u64) -> u64 {
+        assert_eq!(
+            self.admin.get(),
+            self.vm().msg_sender(),
+            "Only admin can change the admin delay"
+        );
+        let current = self.delay();
+        let eta = self.vm().block_timestamp() + current;
+        self.delay.set(U64::from(current));
+        self.pending_delay.set(U64::from(delay));
+        self.delay_eta.set(U64::from(eta));
+        eta
+    }
+
+    /// (pending admin, time from which it can accept)
+    pub fn pending(&self) -> (Address, u64) {
+        (self.pending_admin.get(), self.change_eta.get().to::<u64>())
+    }
+
+    /// (delay, time it takes effect), eta 0 when no change is scheduled
+    pub fn pending_delay(&self) -> (u64, u64) {
+        let eta = self.delay_eta.get().to::<u64>();
+        if eta == 0 || self.vm().block_timestamp() >= eta {
+            return (0, 0);
+        }
+        (self.pending_delay.get().to::<u64>(), eta)
+    }
+
+    /// Delay in force now, a scheduled one once its time has come
+    pub fn delay(&self) -> u64 {
+        let eta = self.delay_eta.get().to::<u64>();
+        if eta != 0 && self.vm().block_timestamp() >= eta {
+            return self.pending_delay.get().to::<u64>();
+        }
+        self.delay.get().to::<u64>()
+    }
+
+    fn clear_pending(&mut self) {
+        self.pending_admin.set(Address::ZERO);
+        self.change_eta.set(U64::ZERO);
+    }
+}
@@ -0,0 +1,54 @@
+//! EIP-712 signatures shared by the contracts that accept signed messages.
+//!
+//! Messages are `sol!` structs, alloy builds their type hash and signing
+//! hash. The signer is recovered with the `ecrecover` precompile at 0x01.
+//! High `s` values are rejected so a signature has exactly one valid form.
+
+// not every contract needs every helper
+#![allow(dead_code)]
+
+use alloc::borrow::Cow;
+use stylus_sdk::{
+    alloy_primitives::{uint, Address, B256, U256},
+    alloy_sol_types::{Eip712Domain, SolStruct},
+    call::RawCall,
+};
+
+/// Half the secp256k1 curve order, the largest `s` accepted
+const MAX_S: U256 = uint!(0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0_U256);
+
+/// Domain with name, version, chain id and the verifying contract
+pub fn domain(name: &'static str, version: &'static str, chain_id: u64, contract: Address) -> Eip712Domain {
+    Eip712Domain::new(
+        Some(Cow::Borrowed(name)),
+        Some(Cow::Borrowed(version)),
+        Some(U256::from(chain_id)),
+        Some(contract),
+        None,
+    )
+}
+
+/// Signer of `message` under `domain`, `None` for an invalid signature
+pub fn signer<T: SolStruct>(message: &T, domain: &Eip712Domain, v: u8, r: B256, s: B256) -> Option<Address> {
+    recover(message.eip712_signing_hash(domain), v, r, s)
+}
+
+/// `ecrecover`, accepting `v` as 27/28 or 0/1
+pub fn recover(hash: B256, v: u8, r: B256, s: B256) -> Option<Address> {
+    let v = if v < 27 { v + 27 } else { v };
+    if (v != 27 && v != 28) || U256::from_be_bytes(s.0) > MAX_S {
+        return None;
+    }
+
+    let mut input = [0u8; 128];
+    input[..32].copy_from_slice(hash.as_slice());
+    input[63] = v;
+    input[64..96].copy_from_slice(r.as_slice());
+    input[96..].copy_from_slice(s.as_slice());
+
+    let output = RawCall::new_static()
+        .call(Address::with_last_byte(1), &input)
+        .ok()?;
+    let signer = Address::from_slice(output.get(12..32)?);
+    (signer != Address::ZERO).then_some(signer)
+}
@@ -1,13 +1,15 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 extern crate alloc;
 
+mod admin;
+mod safe_erc20;
+
+use crate::admin::{Admin, AdminError};
+use crate::safe_erc20::TokenError;
 use stylus_sdk::{
-    alloy_sol_types::{sol, SolCall},
-    contract,
+    alloy_sol_types::sol,
     alloy_primitives::{Address, U256},
-    call::RawCall, 
     prelude::*,
-    storage::StorageAddress,
 };
 
 
@@ -15,40 +17,62 @@ sol_storage! {
     #[entrypoint]
     pub struct Counter {
         address token;
-        address admin;
+        Admin admin;
         mapping(address => uint256) balances;
     }
 }
 
-pub struct Worker {
-    worker_address:Address,
-    amount_paid: U256,
-}
-
 sol! {
-    
-    function balanceOf(address account) returns (uint256);
-    function transfer(address recipient, uint256 value) returns (bool);
     event Transfer(address indexed from, address indexed to, uint256 value);
+    event AdminChanged(address indexed previous_admin, address indexed new_admin);
+    event AdminChangeProposed(address indexed current_admin, address indexed pending_admin, uint64 eta);
+    event AdminChangeCancelled(address indexed pending_admin);
+    event AdminDelayChanged(uint64 delay, uint64 eta);
 }
 
 #[public]
 impl Counter {
     /// (Constructor) Initializes the admin to the caller if not already set.
     pub fn initialize_admin(&mut self) {
-        if Address::from(*self.admin.get()) == Address::default() {
-            self.admin.set(self.vm().msg_sender());
-        }
+        let admin = self.vm().msg_sender();
+        self.admin.initialize(admin);
     }
 
-    /// Allows the current admin to change the admin.
+    /// Proposes a new admin, who has to call `accept_admin` once the admin delay has passed.
     pub fn change_admin(&mut self, new_admin: Address) {
-        assert_eq!(
-            Address::from(*self.admin.get()),
-            self.vm().msg_sender(),
-            "Only admin can change admin"
-        );
-        self.admin.set(new_admin);
+        let eta = self.admin.propose(new_admin);
+        log(self.vm(), AdminChangeProposed { current_admin: self.admin.get(), pending_admin: new_admin, eta });
+    }
+
+    pub fn accept_admin(&mut self) -> Result<(), AdminError> {
+        let previous_admin = self.admin.accept()?;
+        log(self.vm(), AdminChanged { previous_admin, new_admin: self.admin.get() });
+        Ok(())
+    }
+
+    pub fn cancel_admin_change(&mut self) {
+        let pending_admin = self.admin.cancel();
+        log(self.vm(), AdminChangeCancelled { pending_admin });
+    }
+
+    //takes effect once the current delay has passed, then applies to admin changes proposed afterwards
+    pub fn set_admin_delay(&mut self, delay: u64) {
+        let eta = self.admin.set_delay(delay);
+        log(self.vm(), AdminDelayChanged { delay, eta });
+    }
+
+    //(pending admin, time from which it can accept)
+    pub fn pending_admin(&self) -> (Address, u64) {
+        self.admin.pending()
+    }
+
+    pub fn admin_delay(&self) -> u64 {
+        self.admin.delay()
+    }
+
+    //(delay, time it takes effect), eta 0 when no change is scheduled
+    pub fn pending_admin_delay(&self) -> (u64, u64) {
+        self.admin.pending_delay()
     }
 
     /// Sets the token address used for deposits.
@@ -56,25 +80,24 @@ impl Counter {
         self.token.set(_token);
     }
 
-    pub fn deposit(&mut self, amount: U256) -> bool {
+    pub fn deposit(&mut self, amount: U256) -> Result<bool, TokenError> {
         let employer = self.vm().msg_sender();
 
         // Check the external token balance of the employer.
-        let ext_balance = self.token_balance(employer);
+        let ext_balance = self.token_balance(employer)?;
         if ext_balance < amount {
             // Not enough tokens available externally.
-            return false;
+            return Ok(false);
         }
 
-        // Attempt to transfer tokens from the employer to this contract.
-        // This call should use the token's transfer method.
-        let success = Self::_perform_transfer(*self.token, contract::address(), amount);
-        if success {
-            
-            let current = self.balances.get(employer);
-            self.balances.setter(employer).set(current + amount);
-        }
-        success
+        // Pull the tokens from the employer, they need to approve this contract first.
+        // Reverts with a TokenError if the token call fails.
+        let pool = self.vm().contract_address();
+        safe_erc20::transfer_from(self.vm(), *self.token, employer, pool, amount)?;
+
+        let current = self.balances.get(employer);
+        self.balances.setter(employer).set(current + amount);
+        Ok(true)
     }
 
     /// Pay workers from the caller's deposit balance.
@@ -98,10 +121,10 @@ impl Counter {
                 continue;
             }
             
-            let success = Self::_perform_transfer(*self.token, worker_address, amount);
+            let success = safe_erc20::transfer(self.vm(), *self.token, worker_address, amount).is_ok();
             if success {
               
-                available = available - amount;
+                available -= amount;
                 self.balances.setter(employer).set(available);
             }
         }
@@ -119,26 +142,8 @@ impl Counter {
     }
 
    
-    pub fn token_balance(&self, owner: Address) -> U256 {
-        let result = RawCall::new_static().call(
-            alloy_primitives::Address(*self.token.get()),
-            &balanceOfCall { account: owner }.abi_encode(),
-        );
-
-        match result {
-            Ok(data) => U256::from_be_bytes::<32>(data.try_into().unwrap_or([0u8; 32])),
-            Err(_) => U256::from(0), // Returns 0 if the call fails
-        }
-    }
-}
-
-impl Counter {
-    fn _perform_transfer(token_addr: Address, recipient: Address, amount: U256) -> bool {
-        let call_data = transferCall { recipient, value: amount }.abi_encode();
-        let result = RawCall::new().call(token_addr, &call_data);
-        match result {
-            Ok(data) => data.first().copied() == Some(1),
-            Err(_) => false,
-        }
+    /// Token balance of `owner`, reverts with a TokenError if the call fails.
+    pub fn token_balance(&self, owner: Address) -> Result<U256, TokenError> {
+        safe_erc20::balance_of(self.token.get(), owner)
     }
 }
@@ -6,18 +6,19 @@
 //! You can configure the behavior of [`Erc20`] via the [`Erc20Params`] trait,
 //! which allows specifying the name, symbol, and decimals of the token.
 //!
+//! Approvals can also be signed off-chain and submitted by anyone through the
+//! EIP-2612 [`Erc20::permit`], the domain is the token name with version "1".
+//!
 //! Note that this code is unaudited and not fit for production use.
 
 // Imported packages
 use alloc::string::String;
-use alloy_primitives::{Address, U256};
-use alloy_sol_types::sol;
+use alloy_primitives::{Address, B256, U256};
+use alloy_sol_types::{sol, Eip712Domain};
 use core::marker::PhantomData;
-use stylus_sdk::{
-    evm,
-    msg,
-    prelude::*,
-};
+use crate::eip712;
+use crate::pausable::{ActionPaused, Pausable, TRANSFERS};
+use stylus_sdk::prelude::*;
 
 pub trait Erc20Params {
     /// Immutable token name
@@ -39,6 +40,10 @@ sol_storage! {
         mapping(address => mapping(address => uint256)) allowances;
         /// The total supply of the token
         uint256 total_supply;
+        /// Maps owners to the nonce their next permit has to be signed with
+        mapping(address => uint256) nonces;
+        /// Circuit breaker for transfers, see [`Pausable`]
+        Pausable pausable;
         /// Used to allow [`Erc20Params`]
         PhantomData<T> phantom;
     }
@@ -48,9 +53,24 @@ sol_storage! {
 sol! {
     event Transfer(address indexed from, address indexed to, uint256 value);
     event Approval(address indexed owner, address indexed spender, uint256 value);
+    event PauserChanged(address indexed previous_pauser, address indexed new_pauser);
+    event Paused(address indexed pauser, uint8 actions, uint64 until);
+    event Unpaused(address indexed pauser, uint8 actions);
 
     error InsufficientBalance(address from, uint256 have, uint256 want);
     error InsufficientAllowance(address owner, address spender, uint256 have, uint256 want);
+    error ERC2612ExpiredSignature(uint256 deadline);
+    error ERC2612InvalidSigner(address signer, address owner);
+    error ECDSAInvalidSignature();
+    error ERC20InvalidApprover(address approver);
+
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
 }
 
 /// Represents the ways methods may fail.
@@ -58,6 +78,11 @@ sol! {
 pub enum Erc20Error {
     InsufficientBalance(InsufficientBalance),
     InsufficientAllowance(InsufficientAllowance),
+    ActionPaused(ActionPaused),
+    ERC2612ExpiredSignature(ERC2612ExpiredSignature),
+    ERC2612InvalidSigner(ERC2612InvalidSigner),
+    ECDSAInvalidSignature(ECDSAInvalidSignature),
+    ERC20InvalidApprover(ERC20InvalidApprover),
 }
 
 // These methods aren't exposed to other contracts
@@ -72,6 +97,11 @@ impl<T: Erc20Params> Erc20<T> {
         to: Address,
         value: U256,
     ) -> Result<(), Erc20Error> {
+        // Mints and burns are not transfers and keep working while paused
+        self.pausable
+            .when_not_paused(TRANSFERS)
+            .map_err(Erc20Error::ActionPaused)?;
+
         // Decreasing sender balance
         let mut sender_balance = self.balances.setter(from);
         let old_sender_balance = sender_balance.get();
@@ -90,7 +120,7 @@ impl<T: Erc20Params> Erc20<T> {
         to_balance.set(new_to_balance);
 
         // Emitting the transfer event
-        evm::log(Transfer { from, to, value });
+        log(self.vm(), Transfer { from, to, value });
         Ok(())
     }
 
@@ -105,7 +135,7 @@ impl<T: Erc20Params> Erc20<T> {
         self.total_supply.set(self.total_supply.get() + value);
 
         // Emitting the transfer event
-        evm::log(Transfer {
+        log(self.vm(), Transfer {
             from: Address::ZERO,
             to: address,
             value,
@@ -132,7 +162,7 @@ impl<T: Erc20Params> Erc20<T> {
         self.total_supply.set(self.total_supply.get() - value);
 
         // Emitting the transfer event
-        evm::log(Transfer {
+        log(self.vm(), Transfer {
             from: address,
             to: Address::ZERO,
             value,
@@ -140,6 +170,11 @@ impl<T: Erc20Params> Erc20<T> {
 
         Ok(())
     }
+
+    /// EIP-712 domain permits are signed under
+    fn domain(&self) -> Eip712Domain {
+        eip712::domain(T::NAME, "1", self.vm().chain_id(), self.vm().contract_address())
+    }
 }
 
 // These methods are external to other contracts
@@ -173,7 +208,8 @@ impl<T: Erc20Params> Erc20<T> {
 
     /// Transfers `value` tokens from msg::sender() to `to`
     pub fn transfer(&mut self, to: Address, value: U256) -> Result<bool, Erc20Error> {
-        self._transfer(msg::sender(), to, value)?;
+        let sender = self.vm().msg_sender();
+        self._transfer(sender, to, value)?;
         Ok(true)
     }
 
@@ -186,13 +222,14 @@ impl<T: Erc20Params> Erc20<T> {
         value: U256,
     ) -> Result<bool, Erc20Error> {
         // Check msg::sender() allowance
+        let spender = self.vm().msg_sender();
         let mut sender_allowances = self.allowances.setter(from);
-        let mut allowance = sender_allowances.setter(msg::sender());
+        let mut allowance = sender_allowances.setter(spender);
         let old_allowance = allowance.get();
         if old_allowance < value {
             return Err(Erc20Error::InsufficientAllowance(InsufficientAllowance {
                 owner: from,
-                spender: msg::sender(),
+                spender,
                 have: old_allowance,
                 want: value,
             }));
@@ -209,9 +246,10 @@ impl<T: Erc20Params> Erc20<T> {
 
     /// Approves the spenditure of `value` tokens of msg::sender() to `spender`
     pub fn approve(&mut self, spender: Address, value: U256) -> bool {
-        self.allowances.setter(msg::sender()).insert(spender, value);
-        evm::log(Approval {
-            owner: msg::sender(),
+        let owner = self.vm().msg_sender();
+        self.allowances.setter(owner).insert(spender, value);
+        log(self.vm(), Approval {
+            owner,
             spender,
             value,
         });
@@ -222,4 +260,192 @@ impl<T: Erc20Params> Erc20<T> {
     pub fn allowance(&self, owner: Address, spender: Address) -> U256 {
         self.allowances.getter(owner).get(spender)
     }
-}
\ No newline at end of file
+
+    /// Approves `value` tokens of `owner` to `spender` with `owner`'s EIP-712 signature
+    /// (anyone can submit it, each signature is only good for the owner's current nonce)
+    #[allow(clippy::too_many_arguments)]
+    pub fn permit(
+        &mut self,
+        owner: Address,
+        spender: Address,
+        value: U256,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<(), Erc20Error> {
+        if U256::from(self.vm().block_timestamp()) > deadline {
+            return Err(Erc20Error::ERC2612ExpiredSignature(ERC2612ExpiredSignature { deadline }));
+        }
+        if owner == Address::ZERO {
+            return Err(Erc20Error::ERC20InvalidApprover(ERC20InvalidApprover { approver: owner }));
+        }
+
+        // Checking the signature against the current nonce
+        let domain = self.domain();
+        let mut nonce = self.nonces.setter(owner);
+        let current_nonce = nonce.get();
+        let permit = Permit {
+            owner,
+            spender,
+            value,
+            nonce: current_nonce,
+            deadline,
+        };
+        let Some(signer) = eip712::signer(&permit, &domain, v, r, s) else {
+            return Err(Erc20Error::ECDSAInvalidSignature(ECDSAInvalidSignature {}));
+        };
+        if signer != owner {
+            return Err(Erc20Error::ERC2612InvalidSigner(ERC2612InvalidSigner { signer, owner }));
+        }
+        nonce.set(current_nonce + U256::from(1));
+
+        self.allowances.setter(owner).insert(spender, value);
+        log(self.vm(), Approval {
+            owner,
+            spender,
+            value,
+        });
+        Ok(())
+    }
+
+    /// Returns the nonce `owner`'s next permit has to be signed with
+    pub fn nonces(&self, owner: Address) -> U256 {
+        self.nonces.get(owner)
+    }
+
+    /// Returns the EIP-712 domain separator permits are signed under
+    #[selector(name = "DOMAIN_SEPARATOR")]
+    pub fn domain_separator(&self) -> B256 {
+        self.domain().separator()
+    }
+
+    /// Sets the first pauser, meant to be called right after deployment.
+    /// Nobody can pause until it is set
+    pub fn initialize_pauser(&mut self, pauser: Address) {
+        assert_eq!(self.pauser(), Address::ZERO, "Pauser already set");
+        assert_ne!(pauser, Address::ZERO, "Pauser cannot be the zero address");
+        self.pausable.set_pauser(pauser);
+        log(self.vm(), PauserChanged {
+            previous_pauser: Address::ZERO,
+            new_pauser: pauser,
+        });
+    }
+
+    /// Hands the pauser role to `pauser`, only the current pauser can
+    pub fn set_pauser(&mut self, pauser: Address) {
+        assert_eq!(
+            self.pauser(),
+            self.vm().msg_sender(),
+            "Only pauser can set the pauser"
+        );
+        let previous_pauser = self.pauser();
+        self.pausable.set_pauser(pauser);
+        log(self.vm(), PauserChanged {
+            previous_pauser,
+            new_pauser: pauser,
+        });
+    }
+
+    /// Pauses transfers (`actions` = 8), `until` 0 pauses until [`Self::unpause`]
+    pub fn pause(&mut self, actions: u8, until: u64) {
+        self.pausable.pause(actions, until);
+        log(self.vm(), Paused {
+            pauser: self.vm().msg_sender(),
+            actions,
+            until,
+        });
+    }
+
+    /// Lifts the pause on `actions`
+    pub fn unpause(&mut self, actions: u8) {
+        self.pausable.unpause(actions);
+        log(self.vm(), Unpaused {
+            pauser: self.vm().msg_sender(),
+            actions,
+        });
+    }
+
+    /// Returns the current pauser
+    pub fn pauser(&self) -> Address {
+        self.pausable.pauser()
+    }
+
+    /// Returns the paused actions and the time they resume on their own (0 if never)
+    pub fn paused(&self) -> (u8, u64) {
+        (self.pausable.paused_actions(), self.pausable.paused_until())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hostio_stubs;
+    use alloy_sol_types::SolStruct;
+    use stylus_sdk::testing::*;
+
+    struct TestParams;
+    impl Erc20Params for TestParams {
+        const NAME: &'static str = "TEST";
+        const SYMBOL: &'static str = "$TEST";
+        const DECIMALS: u8 = 6;
+    }
+
+    const DEADLINE: u64 = u64::MAX;
+
+    // (v, r, s) of a permit signed by the test key made of `key` bytes
+    fn signed_permit(token: &Erc20<TestParams>, key: u8, owner: Address, spender: Address, value: U256) -> (u8, B256, B256) {
+        let permit = Permit { owner, spender, value, nonce: token.nonces(owner), deadline: U256::from(DEADLINE) };
+        let (_, v, r, s) = hostio_stubs::sign(key, permit.eip712_signing_hash(&token.domain()));
+        (v, r, s)
+    }
+
+    #[test]
+    fn permit_sets_the_allowance_once() {
+        let vm = TestVM::default();
+        let mut token = Erc20::<TestParams>::from(&vm);
+        let (owner, ..) = hostio_stubs::sign(0x01, B256::ZERO);
+        let spender = Address::repeat_byte(0x22);
+        let (v, r, s) = signed_permit(&token, 0x01, owner, spender, U256::from(5));
+
+        assert!(token.permit(owner, spender, U256::from(5), U256::from(DEADLINE), v, r, s).is_ok());
+        assert_eq!(token.allowance(owner, spender), U256::from(5));
+        let replayed = token.permit(owner, spender, U256::from(5), U256::from(DEADLINE), v, r, s);
+        assert!(matches!(replayed, Err(Erc20Error::ERC2612InvalidSigner(_))));
+    }
+
+    #[test]
+    fn permit_rejects_a_signature_from_another_key() {
+        let vm = TestVM::default();
+        let mut token = Erc20::<TestParams>::from(&vm);
+        let (owner, ..) = hostio_stubs::sign(0x01, B256::ZERO);
+        let spender = Address::repeat_byte(0x22);
+        let (v, r, s) = signed_permit(&token, 0x02, owner, spender, U256::from(5));
+
+        let result = token.permit(owner, spender, U256::from(5), U256::from(DEADLINE), v, r, s);
+        assert!(matches!(result, Err(Erc20Error::ERC2612InvalidSigner(_))));
+        assert_eq!(token.allowance(owner, spender), U256::ZERO);
+    }
+
+    #[test]
+    fn permit_rejects_a_bad_signature() {
+        let vm = TestVM::default();
+        let mut token = Erc20::<TestParams>::from(&vm);
+        let (owner, ..) = hostio_stubs::sign(0x01, B256::ZERO);
+        let spender = Address::repeat_byte(0x22);
+
+        let result = token.permit(owner, spender, U256::from(5), U256::from(DEADLINE), 27, B256::ZERO, B256::ZERO);
+        assert!(matches!(result, Err(Erc20Error::ECDSAInvalidSignature(_))));
+    }
+
+    #[test]
+    fn permit_rejects_the_zero_owner() {
+        let vm = TestVM::default();
+        let mut token = Erc20::<TestParams>::from(&vm);
+        let spender = Address::repeat_byte(0x22);
+
+        let result = token.permit(Address::ZERO, spender, U256::MAX, U256::from(DEADLINE), 27, B256::ZERO, B256::ZERO);
+        assert!(matches!(result, Err(Erc20Error::ERC20InvalidApprover(_))));
+        assert_eq!(token.allowance(Address::ZERO, spender), U256::ZERO);
+    }
+}
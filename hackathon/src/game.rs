@@ -0,0 +1,134 @@
+//! Game lifecycle for [`TriviaBase`](crate::TriviaBase).
+//!
+//! A host creates a game with a token and a deadline, anyone can fund its
+//! prize pool while it is open, and the host locks it once play starts.
+//! Players join by paying the game's entry fee into the same pool, once per
+//! address and up to the player cap. When a game has players, only they can
+//! be named winners.
+//! Funding also closes on its own at the deadline. The deployer resolves a
+//! locked game by naming the winners, the prize table and bank fee are the
+//! ones in force when the game was created. A cancelled game refunds every
+//! funder what they put in, each funder claims their own refund.
+//!
+//! Each game holds its own funds, games of the same host never share them.
+//!
+//! A game with an answer key is scored on-chain instead of being resolved by
+//! the deployer. Only games with a player cap can be scored, since the key
+//! reveal ranks every player. The host commits to the key and its number of
+//! questions before the deadline, players commit to their answers until the
+//! deadline and reveal them until `reveal_deadline`, then the host reveals
+//! the key and the contract ranks the players and pays the places. The host
+//! knows the key, so cannot also play a scored game from the host address.
+//! Answers are one byte per question, a player cannot reveal more answers
+//! than there are questions and scores a point for every byte that matches
+//! the key. Ties go to whoever joined first, players who score nothing never
+//! place, and places nobody fills are shared by the filled ones in
+//! proportion to their bps. If the host has not revealed the key
+//! `KEY_REVEAL_GRACE` seconds after `reveal_deadline`, anyone can cancel the
+//! game so funders get refunds.
+
+use alloc::vec::Vec;
+use stylus_sdk::{
+    alloy_primitives::{Address, B256, U256, U8},
+    crypto::keccak,
+};
+
+use crate::Game;
+
+pub const OPEN: u8 = 0;
+pub const LOCKED: u8 = 1;
+pub const RESOLVED: u8 = 2;
+pub const CANCELLED: u8 = 3;
+
+/// Seconds after `reveal_deadline` the host has to reveal the answer key
+pub const KEY_REVEAL_GRACE: u64 = 86_400;
+
+/// `keccak256(abi.encodePacked(game_id, player, answers, salt))`
+pub fn answer_commitment(game_id: U256, player: Address, answers: &[u8], salt: B256) -> B256 {
+    let mut packed = Vec::with_capacity(32 + 20 + answers.len() + 32);
+    packed.extend_from_slice(&game_id.to_be_bytes::<32>());
+    packed.extend_from_slice(player.as_slice());
+    packed.extend_from_slice(answers);
+    packed.extend_from_slice(salt.as_slice());
+    keccak(packed)
+}
+
+/// `keccak256(abi.encodePacked(game_id, answers, salt))`
+pub fn key_commitment(game_id: U256, answers: &[u8], salt: B256) -> B256 {
+    let mut packed = Vec::with_capacity(32 + answers.len() + 32);
+    packed.extend_from_slice(&game_id.to_be_bytes::<32>());
+    packed.extend_from_slice(answers);
+    packed.extend_from_slice(salt.as_slice());
+    keccak(packed)
+}
+
+/// Questions where `answers` matches `key`
+pub fn score(answers: &[u8], key: &[u8]) -> u64 {
+    answers.iter().zip(key).filter(|(answer, correct)| answer == correct).count() as u64
+}
+
+impl Game {
+    pub fn status(&self) -> u8 {
+        self.status.get().to::<u8>()
+    }
+
+    pub fn set_status(&mut self, status: u8) {
+        self.status.set(U8::from(status));
+    }
+
+    /// Open and before the deadline at `now`
+    pub fn is_funding(&self, now: u64) -> bool {
+        self.status() == OPEN && now < self.deadline.get().to::<u64>()
+    }
+
+    /// No room for another player
+    pub fn is_full(&self) -> bool {
+        let cap = self.max_players.get();
+        cap != U256::ZERO && U256::from(self.players.len()) >= cap
+    }
+
+    /// Basis points per place, fixed when the game was created
+    pub fn prize_table(&self) -> Vec<u16> {
+        (0..self.prize_bps.len())
+            .filter_map(|i| self.prize_bps.get(i))
+            .map(|bps| bps.to::<u16>())
+            .collect()
+    }
+
+    /// Scored on-chain from revealed answers rather than resolved by the deployer
+    pub fn is_scored(&self) -> bool {
+        self.answer_key.get() != B256::ZERO
+    }
+
+    /// Players can commit answers at `now`
+    pub fn is_committing(&self, now: u64) -> bool {
+        self.is_live() && now < self.deadline.get().to::<u64>()
+    }
+
+    /// Players can reveal answers at `now`
+    pub fn is_revealing(&self, now: u64) -> bool {
+        self.is_live()
+            && now >= self.deadline.get().to::<u64>()
+            && now < self.reveal_deadline.get().to::<u64>()
+    }
+
+    /// Revealed players with a score against `key`, best first, ties in join order
+    ///
+    /// Bounded by the player cap and the committed key length, which every
+    /// scored game has
+    pub fn ranking(&self, key: &[u8]) -> Vec<(Address, u64)> {
+        let mut ranked: Vec<(Address, u64)> = (0..self.players.len())
+            .filter_map(|i| self.players.get(i))
+            .filter(|player| self.revealed.get(*player))
+            .map(|player| (player, score(&self.answers.getter(player).get_bytes(), key)))
+            .filter(|(_, points)| *points > 0)
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1));
+        ranked
+    }
+
+    /// Not resolved or cancelled yet
+    pub fn is_live(&self) -> bool {
+        matches!(self.status(), OPEN | LOCKED)
+    }
+}
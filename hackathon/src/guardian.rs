@@ -0,0 +1,264 @@
+//! M-of-N guardian approval for emergency withdrawals.
+//!
+//! Shared by the `EmployerPool` contracts. A guardian proposes to move part
+//! of an employer's balance to a destination, the other guardians approve,
+//! and once the threshold is met anyone can execute it before it expires.
+//! The contract itself debits the balance and transfers, this module only
+//! tracks proposals and approvals.
+//!
+//! The admin replaces the guardian set in two steps, a new set is staged
+//! with a time it takes effect from and anyone applies it after that, the
+//! admin or a current guardian can drop it until then. That time is at least
+//! `MIN_SET_DELAY` out, so the admin alone can never swap in a set and use it
+//! in the same block. Replacing the set bumps `set_id`, which closes every
+//! proposal made under the previous set.
+
+use alloc::vec::Vec;
+use stylus_sdk::{
+    alloy_primitives::{Address, U256, U64},
+    alloy_sol_types::sol,
+    prelude::*,
+};
+
+/// Shortest time a staged guardian set waits before it can be applied
+pub const MIN_SET_DELAY: u64 = 86_400;
+
+sol_storage! {
+    pub struct Guardians {
+        address[] members;
+        mapping(address => bool) is_member;
+        uint256 threshold;
+        uint256 set_id;
+        WithdrawalProposal[] proposals;
+        // staged replacement set, `pending_eta` is 0 when nothing is staged
+        address[] pending_members;
+        uint256 pending_threshold;
+        uint64 pending_eta;
+    }
+
+    pub struct WithdrawalProposal {
+        address employer;
+        address token;
+        uint256 amount;
+        address destination;
+        uint64 expiry;
+        /// Guardian set the proposal was made under
+        uint256 set_id;
+        uint256 approvals;
+        mapping(address => bool) approved;
+        bool executed;
+        bool cancelled;
+    }
+}
+
+sol! {
+    error UnknownProposal(uint256 proposal_id);
+    error ProposalClosed(uint256 proposal_id);
+    error NotEnoughApprovals(uint256 proposal_id, uint256 approvals, uint256 threshold);
+    error GuardianChangeNotReady(uint64 eta);
+}
+
+#[derive(SolidityError)]
+pub enum GuardianError {
+    UnknownProposal(UnknownProposal),
+    ProposalClosed(ProposalClosed),
+    NotEnoughApprovals(NotEnoughApprovals),
+    GuardianChangeNotReady(GuardianChangeNotReady),
+}
+
+/// A withdrawal that reached its threshold, for the contract to carry out
+pub struct ApprovedWithdrawal {
+    pub employer: Address,
+    pub token: Address,
+    pub amount: U256,
+    pub destination: Address,
+}
+
+impl Guardians {
+    /// Stages `members` to replace the guardian set after `delay`, or
+    /// `MIN_SET_DELAY` if that is longer, dropping any set staged before.
+    /// Returns the time from which it can be applied
+    pub fn propose_set(&mut self, members: Vec<Address>, threshold: U256, delay: u64) -> u64 {
+        assert!(
+            threshold > U256::ZERO && threshold <= U256::from(members.len()),
+            "Threshold must be between 1 and the number of guardians"
+        );
+        for (i, member) in members.iter().enumerate() {
+            assert_ne!(*member, Address::ZERO, "Guardian cannot be the zero address");
+            assert!(!members[..i].contains(member), "Duplicate guardian");
+        }
+
+        while self.pending_members.pop().is_some() {}
+        for member in members {
+            self.pending_members.push(member);
+        }
+        let eta = self.vm().block_timestamp() + delay.max(MIN_SET_DELAY);
+        self.pending_threshold.set(threshold);
+        self.pending_eta.set(U64::from(eta));
+        eta
+    }
+
+    /// Replaces the guardian set with the staged one, closing all pending
+    /// proposals, returns the new set and threshold
+    pub fn apply_set(&mut self) -> Result<(Vec<Address>, U256), GuardianError> {
+        let eta = self.pending_eta.get().to::<u64>();
+        assert!(eta != 0, "No guardian change staged");
+        if self.vm().block_timestamp() < eta {
+            return Err(GuardianError::GuardianChangeNotReady(GuardianChangeNotReady { eta }));
+        }
+
+        while let Some(old) = self.members.pop() {
+            self.is_member.setter(old).set(false);
+        }
+        let (members, threshold, _) = self.pending_set();
+        for member in &members {
+            self.is_member.setter(*member).set(true);
+            self.members.push(*member);
+        }
+        self.threshold.set(threshold);
+        self.cancel_set();
+        let set_id = self.set_id.get() + U256::from(1);
+        self.set_id.set(set_id);
+        Ok((members, threshold))
+    }
+
+    /// Drops the staged set. Callers check who may do this.
+    pub fn cancel_set(&mut self) {
+        assert!(self.pending_eta.get() != U64::ZERO, "No guardian change staged");
+        while self.pending_members.pop().is_some() {}
+        self.pending_threshold.set(U256::ZERO);
+        self.pending_eta.set(U64::ZERO);
+    }
+
+    /// (members, threshold, time from which it can be applied), eta 0 when nothing is staged
+    pub fn pending_set(&self) -> (Vec<Address>, U256, u64) {
+        let members = (0..self.pending_members.len())
+            .filter_map(|i| self.pending_members.get(i))
+            .collect();
+        (members, self.pending_threshold.get(), self.pending_eta.get().to::<u64>())
+    }
+
+    pub fn members(&self) -> Vec<Address> {
+        (0..self.members.len())
+            .filter_map(|i| self.members.get(i))
+            .collect()
+    }
+
+    pub fn is_member(&self, account: Address) -> bool {
+        self.is_member.get(account)
+    }
+
+    pub fn threshold(&self) -> U256 {
+        self.threshold.get()
+    }
+
+    /// New proposal from a guardian, which counts as its first approval
+    pub fn propose(
+        &mut self,
+        employer: Address,
+        token: Address,
+        amount: U256,
+        destination: Address,
+        expiry: u64,
+    ) -> U256 {
+        let sender = self.vm().msg_sender();
+        assert!(self.is_member.get(sender), "Only guardians can propose");
+        assert_ne!(destination, Address::ZERO, "Destination cannot be the zero address");
+        assert!(expiry > self.vm().block_timestamp(), "Expiry must be in the future");
+
+        let proposal_id = U256::from(self.proposals.len());
+        let set_id = self.set_id.get();
+        let mut proposal = self.proposals.grow();
+        proposal.employer.set(employer);
+        proposal.token.set(token);
+        proposal.amount.set(amount);
+        proposal.destination.set(destination);
+        proposal.expiry.set(U64::from(expiry));
+        proposal.set_id.set(set_id);
+        proposal.approved.setter(sender).set(true);
+        proposal.approvals.set(U256::from(1));
+        proposal_id
+    }
+
+    /// Adds the calling guardian's approval, returns the approval count
+    pub fn approve(&mut self, proposal_id: U256) -> Result<U256, GuardianError> {
+        let sender = self.vm().msg_sender();
+        assert!(self.is_member.get(sender), "Only guardians can approve");
+        self.check_open(proposal_id)?;
+
+        let mut proposal = self.proposals.setter(proposal_id).unwrap();
+        let mut approvals = proposal.approvals.get();
+        if !proposal.approved.get(sender) {
+            proposal.approved.setter(sender).set(true);
+            approvals += U256::from(1);
+            proposal.approvals.set(approvals);
+        }
+        Ok(approvals)
+    }
+
+    pub fn cancel(&mut self, proposal_id: U256) -> Result<(), GuardianError> {
+        self.check_open(proposal_id)?;
+        self.proposals.setter(proposal_id).unwrap().cancelled.set(true);
+        Ok(())
+    }
+
+    /// Marks an approved proposal as executed and hands it back to the contract
+    pub fn execute(&mut self, proposal_id: U256) -> Result<ApprovedWithdrawal, GuardianError> {
+        self.check_open(proposal_id)?;
+        let threshold = self.threshold.get();
+
+        let mut proposal = self.proposals.setter(proposal_id).unwrap();
+        let approvals = proposal.approvals.get();
+        if approvals < threshold {
+            return Err(GuardianError::NotEnoughApprovals(NotEnoughApprovals {
+                proposal_id,
+                approvals,
+                threshold,
+            }));
+        }
+        proposal.executed.set(true);
+        Ok(ApprovedWithdrawal {
+            employer: proposal.employer.get(),
+            token: proposal.token.get(),
+            amount: proposal.amount.get(),
+            destination: proposal.destination.get(),
+        })
+    }
+
+    /// (employer, token, amount, destination, expiry, approvals, open)
+    #[allow(clippy::type_complexity)]
+    pub fn proposal(
+        &self,
+        proposal_id: U256,
+    ) -> Result<(Address, Address, U256, Address, u64, U256, bool), GuardianError> {
+        let open = self.check_open(proposal_id).is_ok();
+        let proposal = self
+            .proposals
+            .getter(proposal_id)
+            .ok_or(GuardianError::UnknownProposal(UnknownProposal { proposal_id }))?;
+        Ok((
+            proposal.employer.get(),
+            proposal.token.get(),
+            proposal.amount.get(),
+            proposal.destination.get(),
+            proposal.expiry.get().to::<u64>(),
+            proposal.approvals.get(),
+            open,
+        ))
+    }
+
+    fn check_open(&self, proposal_id: U256) -> Result<(), GuardianError> {
+        let proposal = self
+            .proposals
+            .getter(proposal_id)
+            .ok_or(GuardianError::UnknownProposal(UnknownProposal { proposal_id }))?;
+        if proposal.executed.get()
+            || proposal.cancelled.get()
+            || proposal.set_id.get() != self.set_id.get()
+            || self.vm().block_timestamp() > proposal.expiry.get().to::<u64>()
+        {
+            return Err(GuardianError::ProposalClosed(ProposalClosed { proposal_id }));
+        }
+        Ok(())
+    }
+}
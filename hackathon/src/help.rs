@@ -1,14 +1,15 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 extern crate alloc;
 
+mod admin;
+mod safe_erc20;
+
+use crate::admin::{Admin, AdminChangeNotReady, AdminError};
+use crate::safe_erc20::{InvalidTokenReturn, TokenCallFailed, TokenError, TokenReturnedFalse};
 use stylus_sdk::{
     alloy_primitives::{Address, U256},
-    alloy_sol_types::{sol, SolCall},
-    call,
-    call::RawCall,
-    contract,
+    alloy_sol_types::sol,
     prelude::*,
-    storage::StorageAddress,
 };
 
 
@@ -16,48 +17,53 @@ sol_storage! {
     #[entrypoint]
     pub struct EmployerPool {
         address token;
-        address admin;
+        Admin admin;
         mapping(address => uint256) balances;
     }
 }
 
-sol_interface! {
-    interface ERC20 {
-    function balanceOf(address account) external view returns (uint256);
-    function transfer(address recipient, uint256 amount)
-        external
-        returns (bool);
-    function transferFrom(address sender, address recipient, uint256 amount)
-        external
-        returns (bool);
-    }
-}
-
-pub struct Worker {
-    worker_address: Address,
-    amount_paid: U256,
-}
-
 sol! {
-
-    function balanceOf(address account) returns (uint256);
-    function transfer(address recipient, uint256 value) returns (bool);
     error InsufficientBalance(uint256 balance);
+    event AdminChanged(address indexed previous_admin, address indexed new_admin);
+    event AdminChangeProposed(address indexed current_admin, address indexed pending_admin, uint64 eta);
+    event AdminChangeCancelled(address indexed pending_admin);
+    event AdminDelayChanged(uint64 delay, uint64 eta);
     //event Transfer(address indexed from, address indexed to, uint256 value);
 }
 
 #[derive(SolidityError)]
 pub enum EmployerPoolError {
     InsufficientBalance(InsufficientBalance),
+    AdminChangeNotReady(AdminChangeNotReady),
+    TokenCallFailed(TokenCallFailed),
+    TokenReturnedFalse(TokenReturnedFalse),
+    InvalidTokenReturn(InvalidTokenReturn),
+}
+
+impl From<AdminError> for EmployerPoolError {
+    fn from(err: AdminError) -> Self {
+        match err {
+            AdminError::AdminChangeNotReady(e) => EmployerPoolError::AdminChangeNotReady(e),
+        }
+    }
+}
+
+impl From<TokenError> for EmployerPoolError {
+    fn from(err: TokenError) -> Self {
+        match err {
+            TokenError::TokenCallFailed(e) => EmployerPoolError::TokenCallFailed(e),
+            TokenError::TokenReturnedFalse(e) => EmployerPoolError::TokenReturnedFalse(e),
+            TokenError::InvalidTokenReturn(e) => EmployerPoolError::InvalidTokenReturn(e),
+        }
+    }
 }
 
 #[public]
 impl EmployerPool {
     /// (Constructor) intialize the admin
     pub fn initialize_admin(&mut self) {
-        if Address::from(*self.admin.get()) == Address::default() {
-            self.admin.set(self.vm().msg_sender());
-        }
+        let admin = self.vm().msg_sender();
+        self.admin.initialize(admin);
     }
     //intialize a token for now, multi token will be added for future. mock usdc only now
     pub fn set_address(&mut self, _token: Address) {
@@ -69,37 +75,65 @@ impl EmployerPool {
         self.token.set(_token);
     }
 
-    
+    //proposes new_admin, who has to call accept_admin once admin_delay has passed
     pub fn change_admin(&mut self, new_admin: Address) {
-        assert_eq!(
-            Address::from(*self.admin.get()),
-            self.vm().msg_sender(),
-            "Only admin can change admin"
-        );
-        self.admin.set(new_admin);
+        let eta = self.admin.propose(new_admin);
+        log(self.vm(), AdminChangeProposed { current_admin: self.admin.get(), pending_admin: new_admin, eta });
+    }
+
+    pub fn accept_admin(&mut self) -> Result<(), EmployerPoolError> {
+        let previous_admin = self.admin.accept()?;
+        log(self.vm(), AdminChanged { previous_admin, new_admin: self.admin.get() });
+        Ok(())
+    }
+
+    pub fn cancel_admin_change(&mut self) {
+        let pending_admin = self.admin.cancel();
+        log(self.vm(), AdminChangeCancelled { pending_admin });
+    }
+
+    //takes effect once the current delay has passed, then applies to admin changes proposed afterwards
+    pub fn set_admin_delay(&mut self, delay: u64) {
+        let eta = self.admin.set_delay(delay);
+        log(self.vm(), AdminDelayChanged { delay, eta });
+    }
+
+    //(pending admin, time from which it can accept)
+    pub fn pending_admin(&self) -> (Address, u64) {
+        self.admin.pending()
+    }
+
+    pub fn admin_delay(&self) -> u64 {
+        self.admin.delay()
+    }
+
+    //(delay, time it takes effect), eta 0 when no change is scheduled
+    pub fn pending_admin_delay(&self) -> (u64, u64) {
+        self.admin.pending_delay()
     }
 
     //deposits after intialized address
 
-    pub fn deposit(&mut self, amount: U256) -> bool {
+    pub fn deposit(&mut self, amount: U256) -> Result<bool, EmployerPoolError> {
         let employer = self.vm().msg_sender();
         let current = self.balances.get(employer);
 
         // Check the external token balance of the employer.
-        let ext_balance = self.token_balance(employer);
+        let ext_balance = self.token_balance(employer)?;
         if ext_balance < amount {
-            return false;
+            return Ok(false);
         }
 
-        let success = Self::transfer_from_token(self, employer, contract::address(), amount);
+        let pool = self.vm().contract_address();
+        Self::transfer_from_token(self, employer, pool, amount)?;
         
         self.balances.setter(employer).set(current + amount);
-        success
+        Ok(true)
     }
     //total pased from the fe 
     pub fn pay_workers(&mut self, workers: Vec<(Address, U256)>, _total:U256) -> Result<bool, EmployerPoolError> {
         let employer = self.vm().msg_sender();
-        let mut bal = Self::employer_balance(&self, employer);
+        let mut bal = Self::employer_balance(self, employer);
 
         if _total > bal {
             return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
@@ -111,8 +145,8 @@ impl EmployerPool {
                 if bal < amount {
                    continue;
                 }
-                let success = Self::transfer_token(self, worker_address, amount);
-                bal = bal - amount;
+                Self::transfer_token(self, worker_address, amount)?;
+                bal -= amount;
                 self.balances.setter(employer).set(bal);
             }
         }
@@ -124,7 +158,7 @@ impl EmployerPool {
     //admin only admin can call
     //fe checks if the total amount is enough before calling this function
     pub fn auto_pay_workers(&mut self, employer:Address ,workers: Vec<(Address, U256)>, _total:U256) -> Result<bool, EmployerPoolError> {
-        let mut bal = Self::employer_balance(&self, employer);
+        let mut bal = Self::employer_balance(self, employer);
 
         if _total > bal {
             return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
@@ -142,8 +176,8 @@ impl EmployerPool {
             if bal < amount {
                 continue;
             }
-            let success = Self::transfer_token(self, worker_address, amount);
-            bal = bal - amount;
+            Self::transfer_token(self, worker_address, amount)?;
+            bal -= amount;
             self.balances.setter(employer).set(bal);
         }
         Ok(true)
@@ -151,15 +185,15 @@ impl EmployerPool {
 
     pub fn transfer_by_employer(&mut self, _recipient:Address ,_amount : U256) -> Result<bool, EmployerPoolError> {
         let employer = self.vm().msg_sender();
-        let mut bal = Self::employer_balance(&self, employer);
+        let mut bal = Self::employer_balance(self, employer);
 
         if _amount > bal {
             return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
                 balance: bal,
             }));
         } else {
-            Self::transfer_token(self, _recipient, _amount);
-            bal = bal - _amount;
+            Self::transfer_token(self, _recipient, _amount)?;
+            bal -= _amount;
             self.balances.setter(employer).set(bal);
         }
         Ok(true)
@@ -173,23 +207,16 @@ impl EmployerPool {
         self.balances.get(self.vm().msg_sender())
     }
 
-    pub fn token_balance(&self, owner: Address) -> U256 {
-        let result = RawCall::new_static().call(
-            alloy_primitives::Address(*self.token.get()),
-            &balanceOfCall { account: owner }.abi_encode(),
-        );
-
-        match result {
-            Ok(data) => U256::from_be_bytes::<32>(data.try_into().unwrap_or([0u8; 32])),
-            Err(_) => U256::from(0), // Returns 0 if the call fails
-        }
+    //reverts when the token call fails instead of reporting 0
+    pub fn token_balance(&self, owner: Address) -> Result<U256, EmployerPoolError> {
+        Ok(safe_erc20::balance_of(self.token.get(), owner)?)
     }
 
     pub fn emergency_withdraw(&mut self, employer_addres:Address ,_amount:U256) -> Result<(), EmployerPoolError> {
         //in case of emergency... most esp lost wallet
         //money glitch found. FUCK!
 
-        let mut bal = Self::employer_balance(&self, employer_addres);
+        let mut bal = Self::employer_balance(self, employer_addres);
 
         if _amount > bal {
             return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
@@ -204,15 +231,14 @@ impl EmployerPool {
             "Only admin can call this booga ooga restrictive function"
         );
 
-        Self::transfer_token(self, admin, _amount);
-        bal = bal - _amount;
+        Self::transfer_token(self, admin, _amount)?;
+        bal -= _amount;
         self.balances.setter(employer_addres).set(bal);
         Ok(())
     }
 
-    fn balance(&self) -> U256{
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-        token.balance()
+    fn balance(&self) -> Result<U256, EmployerPoolError>{
+        self.token_balance(self.vm().contract_address())
     }
 }
 
@@ -220,17 +246,11 @@ impl EmployerPool {
 
     //internal func
 
-    fn transfer_from_token(&mut self, from: Address, to: Address, amount: U256) -> bool {
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-
-        token
-            .transfer_from(self, from, to, amount)
-            .expect("approve token first")
+    //token calls go through safe_erc20
+    fn transfer_from_token(&mut self, from: Address, to: Address, amount: U256) -> Result<(), EmployerPoolError> {
+        Ok(safe_erc20::transfer_from(self.vm(), self.token.get(), from, to, amount)?)
     }
-    fn transfer_token(&mut self, to: Address, amount: U256) -> bool {
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-        token
-            .transfer(self, to, amount)
-            .expect("approve token first")
+    fn transfer_token(&mut self, to: Address, amount: U256) -> Result<(), EmployerPoolError> {
+        Ok(safe_erc20::transfer(self.vm(), self.token.get(), to, amount)?)
     } //contract balance
 }
@@ -0,0 +1,159 @@
+//! Native stand-ins for the host functions behind `RawCall` and the
+//! deprecated `contract::address`.
+//!
+//! `safe_erc20` and `eip712` call through `RawCall`, which goes straight to
+//! the Stylus host instead of through `self.vm()`, so `TestVM` cannot mock
+//! those calls and the unit test binary would not link. Calls to the
+//! `ecrecover` precompile are answered natively, so signatures made with
+//! [`sign`] recover as they would on-chain. Any other call goes to the
+//! handler a test installs with [`on_call`], e.g. a token that calls back
+//! into the contract the way `reentrant_token.rs` does. A panic in the
+//! handler reverts the call with the panic message, like a revert in the
+//! called contract. Without a handler a call panics.
+
+// not every test binary signs messages
+#![allow(dead_code)]
+
+use core::slice;
+use std::{cell::RefCell, panic, rc::Rc};
+
+use ethers::{
+    core::types::{RecoveryMessage, Signature, H256, U256 as EthU256},
+    signers::LocalWallet,
+};
+use stylus_sdk::alloy_primitives::{Address, B256};
+
+type Handler = Rc<dyn Fn(Address, &[u8]) -> Result<Vec<u8>, Vec<u8>>>;
+
+thread_local! {
+    static HANDLER: RefCell<Option<Handler>> = const { RefCell::new(None) };
+    static RETURN_DATA: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Answers every call the test thread makes, other than to `ecrecover`,
+/// with `handler(contract, calldata)`
+pub fn on_call(handler: impl Fn(Address, &[u8]) -> Result<Vec<u8>, Vec<u8>> + 'static) {
+    HANDLER.with(|current| *current.borrow_mut() = Some(Rc::new(handler)));
+}
+
+/// Runs the installed handler, turning a panic into a revert with its message
+fn handle(to: Address, data: &[u8]) -> Result<Vec<u8>, Vec<u8>> {
+    // cloned out so the handler can make calls of its own
+    let handler = HANDLER
+        .with(|current| current.borrow().clone())
+        .expect("token calls need a handler, see hostio_stubs::on_call");
+    panic::catch_unwind(panic::AssertUnwindSafe(|| handler(to, data))).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|message| message.to_string()))
+            .unwrap_or_default();
+        Err(message.into_bytes())
+    })
+}
+
+/// Signs `hash` with the key made of 32 `key` bytes, returns (signer, v, r, s)
+pub fn sign(key: u8, hash: B256) -> (Address, u8, B256, B256) {
+    let wallet = LocalWallet::from_bytes(&[key; 32]).expect("valid test key");
+    let signature = wallet.sign_hash(H256(hash.0)).expect("signed");
+    let mut r = [0u8; 32];
+    let mut s = [0u8; 32];
+    signature.r.to_big_endian(&mut r);
+    signature.s.to_big_endian(&mut s);
+    let signer = Address::from(ethers::signers::Signer::address(&wallet).0);
+    (signer, signature.v as u8, B256::from(r), B256::from(s))
+}
+
+/// The `ecrecover` precompile, empty output for a signature that does not recover
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let mut padded = [0u8; 128];
+    let len = input.len().min(128);
+    padded[..len].copy_from_slice(&input[..len]);
+
+    let signature = Signature {
+        r: EthU256::from_big_endian(&padded[64..96]),
+        s: EthU256::from_big_endian(&padded[96..]),
+        v: u64::from(padded[63]),
+    };
+    match signature.recover(RecoveryMessage::Hash(H256::from_slice(&padded[..32]))) {
+        Ok(signer) => {
+            let mut output = vec![0u8; 32];
+            output[12..].copy_from_slice(signer.as_bytes());
+            output
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Runs a call and keeps its output for `read_return_data`, 0 on success
+fn dispatch(contract: *const u8, calldata: *const u8, calldata_len: usize, return_data_len: *mut usize) -> u8 {
+    let (to, data) = unsafe {
+        (
+            Address::from_slice(slice::from_raw_parts(contract, 20)),
+            slice::from_raw_parts(calldata, calldata_len),
+        )
+    };
+    let result = if to == Address::with_last_byte(1) { Ok(ecrecover(data)) } else { handle(to, data) };
+    let (status, output) = match result {
+        Ok(output) => (0, output),
+        Err(output) => (1, output),
+    };
+    unsafe { *return_data_len = output.len() };
+    RETURN_DATA.with(|data| *data.borrow_mut() = output);
+    status
+}
+
+#[no_mangle]
+extern "C" fn call_contract(
+    contract: *const u8,
+    calldata: *const u8,
+    calldata_len: usize,
+    _value: *const u8,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    dispatch(contract, calldata, calldata_len, return_data_len)
+}
+
+#[no_mangle]
+extern "C" fn delegate_call_contract(
+    _contract: *const u8,
+    _calldata: *const u8,
+    _calldata_len: usize,
+    _gas: u64,
+    _return_data_len: *mut usize,
+) -> u8 {
+    unimplemented!("delegate calls are not available in unit tests")
+}
+
+#[no_mangle]
+extern "C" fn static_call_contract(
+    contract: *const u8,
+    calldata: *const u8,
+    calldata_len: usize,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    dispatch(contract, calldata, calldata_len, return_data_len)
+}
+
+#[no_mangle]
+extern "C" fn read_return_data(dest: *mut u8, offset: usize, size: usize) -> usize {
+    RETURN_DATA.with(|data| {
+        let data = data.borrow();
+        let available = data.get(offset..).unwrap_or_default();
+        let len = available.len().min(size);
+        unsafe { core::ptr::copy_nonoverlapping(available.as_ptr(), dest, len) };
+        len
+    })
+}
+
+#[no_mangle]
+extern "C" fn return_data_size() -> usize {
+    RETURN_DATA.with(|data| data.borrow().len())
+}
+
+#[no_mangle]
+extern "C" fn contract_address(_address: *mut u8) {
+    unimplemented!("use self.vm().contract_address() in code under test")
+}
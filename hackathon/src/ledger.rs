@@ -0,0 +1,160 @@
+//! Payment history for [`EmployerPool`](crate::EmployerPool).
+//!
+//! Keeps lifetime deposit and payout totals per employer and token, what
+//! each employer has paid each worker, and an append-only log of every
+//! payment with indexes by employer and by worker. The views page through
+//! the log so the history can be rebuilt without an external indexer.
+//!
+//! Every call that pays workers opens a new run, and all payments it makes
+//! share the run id. A payroll is one run, so is a stream withdrawal, a grant
+//! claim or a distribution claim. Ledger run ids are unrelated to the
+//! distribution `run_id`. Payments to workers on pull payments are recorded
+//! when they are credited, not when the worker claims.
+
+use alloc::vec::Vec;
+use stylus_sdk::{
+    alloy_primitives::{Address, U256, U64},
+    prelude::*,
+    storage::{StorageU256, StorageVec},
+};
+
+sol_storage! {
+    pub struct Ledger {
+        uint256 runs;
+        // employer => token => amount
+        mapping(address => mapping(address => uint256)) deposited;
+        mapping(address => mapping(address => uint256)) paid_out;
+        // employer => worker => token => amount
+        mapping(address => mapping(address => mapping(address => uint256))) paid_to_worker;
+        PaymentRecord[] records;
+        mapping(address => uint256[]) employer_records;
+        mapping(address => uint256[]) worker_records;
+    }
+
+    pub struct PaymentRecord {
+        uint256 run_id;
+        address employer;
+        address worker;
+        address token;
+        uint256 amount;
+        uint64 timestamp;
+    }
+}
+
+/// (run_id, employer, worker, token, amount, timestamp)
+pub type RecordView = (U256, Address, Address, Address, U256, u64);
+
+impl Ledger {
+    /// Opens a run for the payments of a single call
+    pub fn start_run(&mut self) -> U256 {
+        let run_id = self.runs.get();
+        self.runs.set(run_id + U256::from(1));
+        run_id
+    }
+
+    pub fn record_deposit(&mut self, employer: Address, token: Address, amount: U256) {
+        let mut deposited = self.deposited.setter(employer);
+        let mut total = deposited.setter(token);
+        let new_total = total.get() + amount;
+        total.set(new_total);
+    }
+
+    /// Appends a payment and adds it to the totals
+    pub fn record_payment(
+        &mut self,
+        run_id: U256,
+        now: u64,
+        employer: Address,
+        worker: Address,
+        token: Address,
+        amount: U256,
+    ) {
+        let mut paid_out = self.paid_out.setter(employer);
+        let mut total = paid_out.setter(token);
+        let new_total = total.get() + amount;
+        total.set(new_total);
+
+        let mut by_employer = self.paid_to_worker.setter(employer);
+        let mut by_worker = by_employer.setter(worker);
+        let mut paid = by_worker.setter(token);
+        let new_paid = paid.get() + amount;
+        paid.set(new_paid);
+
+        let id = U256::from(self.records.len());
+        let mut record = self.records.grow();
+        record.run_id.set(run_id);
+        record.employer.set(employer);
+        record.worker.set(worker);
+        record.token.set(token);
+        record.amount.set(amount);
+        record.timestamp.set(U64::from(now));
+        self.employer_records.setter(employer).push(id);
+        self.worker_records.setter(worker).push(id);
+    }
+
+    pub fn deposited(&self, employer: Address, token: Address) -> U256 {
+        self.deposited.getter(employer).get(token)
+    }
+
+    pub fn paid_out(&self, employer: Address, token: Address) -> U256 {
+        self.paid_out.getter(employer).get(token)
+    }
+
+    pub fn paid_to_worker(&self, employer: Address, worker: Address, token: Address) -> U256 {
+        self.paid_to_worker.getter(employer).getter(worker).get(token)
+    }
+
+    pub fn record_count(&self) -> U256 {
+        U256::from(self.records.len())
+    }
+
+    pub fn employer_record_count(&self, employer: Address) -> U256 {
+        U256::from(self.employer_records.getter(employer).len())
+    }
+
+    pub fn worker_record_count(&self, worker: Address) -> U256 {
+        U256::from(self.worker_records.getter(worker).len())
+    }
+
+    /// Up to `limit` records of the whole log from `offset` on, oldest first
+    pub fn records(&self, offset: U256, limit: U256) -> Vec<RecordView> {
+        page(self.records.len(), offset, limit)
+            .filter_map(|i| self.record(U256::from(i)))
+            .collect()
+    }
+
+    /// Same as [`Self::records`], over the employer's records only
+    pub fn employer_records(&self, employer: Address, offset: U256, limit: U256) -> Vec<RecordView> {
+        self.indexed(&self.employer_records.getter(employer), offset, limit)
+    }
+
+    /// Same as [`Self::records`], over the worker's records only
+    pub fn worker_records(&self, worker: Address, offset: U256, limit: U256) -> Vec<RecordView> {
+        self.indexed(&self.worker_records.getter(worker), offset, limit)
+    }
+
+    fn indexed(&self, ids: &StorageVec<StorageU256>, offset: U256, limit: U256) -> Vec<RecordView> {
+        page(ids.len(), offset, limit)
+            .filter_map(|i| self.record(ids.get(i)?))
+            .collect()
+    }
+
+    fn record(&self, id: U256) -> Option<RecordView> {
+        let record = self.records.getter(id)?;
+        Some((
+            record.run_id.get(),
+            record.employer.get(),
+            record.worker.get(),
+            record.token.get(),
+            record.amount.get(),
+            record.timestamp.get().to::<u64>(),
+        ))
+    }
+}
+
+/// Indexes `offset..offset + limit`, clamped to `len`
+fn page(len: usize, offset: U256, limit: U256) -> core::ops::Range<usize> {
+    let start = offset.saturating_to::<usize>().min(len);
+    let end = start.saturating_add(limit.saturating_to::<usize>()).min(len);
+    start..end
+}
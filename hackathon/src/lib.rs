@@ -1,236 +1,2486 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
+#![recursion_limit = "256"]
 extern crate alloc;
 
+pub mod merkle;
+mod admin;
+mod eip712;
+mod guardian;
+mod ledger;
+mod operator;
+mod pausable;
+mod reentrancy;
+mod safe_erc20;
+mod registry;
+mod schedule;
+mod stream;
+mod vesting;
+#[cfg(test)]
+mod hostio_stubs;
+
+use crate::admin::{Admin, AdminChangeNotReady, AdminError};
+use crate::guardian::{
+    GuardianChangeNotReady, GuardianError, Guardians, NotEnoughApprovals, ProposalClosed, UnknownProposal,
+};
+use crate::ledger::{Ledger, RecordView};
+use crate::pausable::{ActionPaused, Pausable, DEPOSITS, PAYOUTS};
+use crate::reentrancy::ReentrancyGuard;
+use crate::safe_erc20::{InvalidTokenReturn, TokenCallFailed, TokenError, TokenReturnedFalse};
 use stylus_sdk::{
-    alloy_primitives::{Address, U256},
-    alloy_sol_types::{sol, SolCall},
-    call,
-    call::RawCall,
-    contract,
+    alloy_primitives::{Address, B256, U256, U64},
+    alloy_sol_types::{sol, Eip712Domain},
     prelude::*,
-    storage::StorageAddress,
+    storage::{StorageGuard, StorageGuardMut},
 };
 
 
 sol_storage! {
     #[entrypoint]
     pub struct EmployerPool {
+        // default token, used by the single token entry points
         address token;
-        address admin;
-        mapping(address => uint256) balances;
+        // two step admin handover
+        Admin admin;
+        // employer => token => amount
+        mapping(address => mapping(address => uint256)) balances;
+        mapping(address => bool) allowed_tokens;
+        address[] token_list;
+        Stream[] streams;
+        mapping(address => uint256[]) employer_streams;
+        mapping(address => uint256[]) worker_streams;
+        Grant[] grants;
+        mapping(address => uint256[]) employer_grants;
+        mapping(address => uint256[]) worker_grants;
+        // employer => worker => registry entry
+        mapping(address => mapping(address => Worker)) workers;
+        mapping(address => address[]) rosters;
+        // employers that credit workers instead of transferring to them
+        mapping(address => bool) pull_payments;
+        // worker => token => amount waiting to be claimed
+        mapping(address => mapping(address => uint256)) claimable;
+        // run id => run, see merkle::run_id
+        mapping(uint256 => Distribution) distributions;
+        // employer => nonce of the next distribution run
+        mapping(address => uint64) distribution_nonces;
+        // run id => word => claimed bits
+        mapping(uint256 => mapping(uint256 => uint256)) distribution_claims;
+        // employer => operator => token => grant
+        mapping(address => mapping(address => mapping(address => Operator))) operators;
+        Guardians guardians;
+        Pausable pausable;
+        ReentrancyGuard reentrancy;
+        // seconds between request_withdrawal and execute_withdrawal
+        uint64 withdrawal_notice;
+        // employer => token => pending withdrawal
+        mapping(address => mapping(address => WithdrawalRequest)) withdrawal_requests;
+        // token => amount the pool should hold, deposits received minus transfers out
+        mapping(address => uint256) token_holdings;
+        // employer => nonce the next signed payroll has to use
+        mapping(address => uint256) payroll_nonces;
+        Schedule[] schedules;
+        mapping(address => uint256[]) employer_schedules;
+        Ledger ledger;
+    }
+
+    /// Salary stream, see [`stream`]
+    pub struct Stream {
+        address employer;
+        address worker;
+        address token;
+        /// Tokens released per second
+        uint256 rate;
+        uint64 start;
+        /// 0 means the stream runs until cancelled or out of funds
+        uint64 stop;
+        /// Total amount escrowed from the employer's balance
+        uint256 deposit;
+        uint256 withdrawn;
+        /// Amount accrued up to `checkpoint_time`
+        uint256 accrued;
+        uint64 checkpoint_time;
+        bool paused;
+        bool active;
+    }
+
+    /// Vesting grant, see [`vesting`]
+    pub struct Grant {
+        address employer;
+        address worker;
+        address token;
+        /// Locked amount, reduced to the vested amount on revoke
+        uint256 total;
+        uint256 claimed;
+        uint64 start;
+        /// Seconds after `start` before anything vests
+        uint64 cliff;
+        uint64 duration;
+        /// 0 for a linear release, otherwise the release interval in seconds
+        uint64 step;
+        bool revoked;
+    }
+
+    /// Registered worker, see [`registry`]
+    pub struct Worker {
+        address token;
+        uint256 salary;
+        /// Seconds between payments
+        uint64 pay_period;
+        uint64 last_paid;
+        bool active;
+    }
+
+    /// Merkle payroll run, see [`merkle`]
+    pub struct Distribution {
+        address employer;
+        address token;
+        bytes32 root;
+        uint256 total;
+        uint256 claimed;
+        /// After this the employer can reclaim what is left
+        uint64 expiry;
+        bool reclaimed;
+    }
+
+    /// Employer withdrawal waiting out the notice period
+    pub struct WithdrawalRequest {
+        uint256 amount;
+        /// Time from which it can be executed, 0 when nothing is requested
+        uint64 eta;
+    }
+
+    /// Recurring payroll, see [`schedule`]
+    pub struct Schedule {
+        address employer;
+        address token;
+        address[] recipients;
+        uint256[] amounts;
+        /// Seconds between runs, 0 runs once
+        uint64 interval;
+        uint64 next_run;
+        /// Paid from the employer's balance to whoever executes a run
+        uint256 tip;
+        bool active;
     }
-}
 
-sol_interface! {
-    interface ERC20 {
-    function balanceOf(address account) external view returns (uint256);
-    function transfer(address recipient, uint256 amount)
-        external
-        returns (bool);
-    function transferFrom(address sender, address recipient, uint256 amount)
-        external
-        returns (bool);
+    /// Payroll operator grant, see [`operator`]
+    pub struct Operator {
+        /// 0 means no cap
+        uint256 cap;
+        /// 0 means the cap covers the whole grant
+        uint64 cap_period;
+        uint64 period_start;
+        uint256 spent;
+        /// 0 means the grant never expires
+        uint64 expiry;
+        bool active;
     }
 }
 
-pub struct Worker {
-    worker_address: Address,
-    amount_paid: U256,
+sol! {
+    error InsufficientBalance(uint256 balance);
+    error TokenNotAllowed(address token);
+    error UnknownStream(uint256 stream_id);
+    error StreamNotActive(uint256 stream_id);
+    error UnknownGrant(uint256 grant_id);
+    error GrantAlreadyRevoked(uint256 grant_id);
+    error WorkerNotRegistered(address worker);
+    error WorkerAlreadyRegistered(address worker);
+    error UnknownDistribution(uint256 run_id);
+    error DistributionExpired(uint256 run_id);
+    error DistributionNotExpired(uint256 run_id);
+    error AlreadyClaimed(uint256 run_id, uint256 index);
+    error InvalidProof(uint256 run_id, uint256 index);
+    error InvalidRunId(uint256 expected, uint256 run_id);
+    error UnpayableWorker(address worker, uint256 amount);
+    error NotOperator(address employer, address operator);
+    error OperatorCapExceeded(address operator, uint256 remaining);
+    error NoWithdrawalRequest(address employer, address token);
+    error WithdrawalNotReady(uint64 eta);
+    error FundsCommitted(uint256 available);
+    error TokenBalanceMismatch(address token, uint256 tracked, uint256 actual);
+    error UnexpectedTransferAmount(address token, uint256 amount, uint256 received);
+    error PayrollExpired(uint256 deadline);
+    error InvalidPayrollNonce(uint256 expected, uint256 nonce);
+    error InvalidPayrollSigner(address signer, address employer);
+    error InvalidSignature();
+    error PayrollLengthMismatch(uint256 workers, uint256 amounts);
+    error UnknownSchedule(uint256 schedule_id);
+    error ScheduleNotActive(uint256 schedule_id);
+    error ScheduleNotDue(uint256 schedule_id, uint64 next_run);
 }
 
+// EIP-712 message an employer signs to have anyone run a payroll from their balance in token
 sol! {
+    struct Payroll {
+        address employer;
+        address token;
+        address[] workers;
+        uint256[] amounts;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
 
-    function balanceOf(address account) returns (uint256);
-    function transfer(address recipient, uint256 value) returns (bool);
-    error InsufficientBalance(uint256 balance);
-    //event Transfer(address indexed from, address indexed to, uint256 value);
+// events go through this macro so the export-abi build can print them too,
+// stylus only exports functions and errors
+macro_rules! pool_events {
+    ($($event:tt)*) => {
+        sol! { $($event)* }
+
+        #[cfg(feature = "export-abi")]
+        const EVENTS_ABI: &str = stringify!($($event)*);
+    };
+}
+
+pool_events! {
+    event AdminChanged(address indexed previous_admin, address indexed new_admin);
+    event AdminChangeProposed(address indexed current_admin, address indexed pending_admin, uint64 eta);
+    event AdminChangeCancelled(address indexed pending_admin);
+    event AdminDelayChanged(uint64 delay, uint64 eta);
+    event TokenChanged(address indexed previous_token, address indexed new_token);
+    event TokenAllowlisted(address indexed token, bool allowed);
+    event TokenHoldingsSynced(address indexed token, uint256 tracked, uint256 actual);
+    event Deposited(address indexed employer, address indexed token, uint256 amount);
+    event WorkerPaid(address indexed employer, address indexed worker, address token, uint256 amount);
+    event PayrollExecuted(address indexed employer, uint256 workers_paid, uint256 workers_skipped);
+    event EmployerTransfer(address indexed employer, address indexed recipient, address token, uint256 amount);
+    event EmergencyWithdrawal(address indexed employer, address indexed to, address token, uint256 amount);
+    event StreamCreated(uint256 indexed stream_id, address indexed employer, address indexed worker, address token, uint256 rate, uint256 amount);
+    event StreamWithdrawn(uint256 indexed stream_id, address indexed worker, uint256 amount);
+    event StreamPaused(uint256 indexed stream_id);
+    event StreamResumed(uint256 indexed stream_id);
+    event StreamToppedUp(uint256 indexed stream_id, uint256 amount);
+    event StreamCancelled(uint256 indexed stream_id, uint256 refund);
+    event GrantCreated(uint256 indexed grant_id, address indexed employer, address indexed worker, address token, uint256 amount);
+    event GrantClaimed(uint256 indexed grant_id, address indexed worker, uint256 amount);
+    event GrantRevoked(uint256 indexed grant_id, uint256 refund);
+    event WorkerRegistered(address indexed employer, address indexed worker, address token, uint256 salary, uint64 pay_period);
+    event WorkerUpdated(address indexed employer, address indexed worker, address token, uint256 salary, uint64 pay_period);
+    event WorkerRemoved(address indexed employer, address indexed worker);
+    event PullPaymentsSet(address indexed employer, bool enabled);
+    event Claimed(address indexed worker, address indexed recipient, address token, uint256 amount);
+    event DistributionCreated(uint256 indexed run_id, address indexed employer, address token, bytes32 root, uint256 total, uint64 expiry);
+    event DistributionClaimed(uint256 indexed run_id, address indexed worker, uint256 index, uint256 amount);
+    event DistributionReclaimed(uint256 indexed run_id, uint256 amount);
+    event OperatorGranted(address indexed employer, address indexed operator, address token, uint256 cap, uint64 cap_period, uint64 expiry);
+    event OperatorRevoked(address indexed employer, address indexed operator, address token);
+    event GuardiansChangeProposed(address[] guardians, uint256 threshold, uint64 eta);
+    event GuardiansChangeCancelled();
+    event GuardiansChanged(address[] guardians, uint256 threshold);
+    event WithdrawalProposed(uint256 indexed proposal_id, address indexed employer, address token, uint256 amount, address destination, uint64 expiry);
+    event WithdrawalApproved(uint256 indexed proposal_id, address indexed guardian, uint256 approvals);
+    event WithdrawalCancelled(uint256 indexed proposal_id);
+    event OperatorPayment(address indexed employer, address indexed operator, address indexed worker, address token, uint256 amount);
+    event PauserChanged(address indexed previous_pauser, address indexed new_pauser);
+    event Paused(address indexed pauser, uint8 actions, uint64 until);
+    event Unpaused(address indexed pauser, uint8 actions);
+    event WithdrawalNoticeChanged(uint64 notice);
+    event WithdrawalRequested(address indexed employer, address indexed token, uint256 amount, uint64 eta);
+    event WithdrawalRequestCancelled(address indexed employer, address indexed token);
+    event EmployerWithdrawal(address indexed employer, address indexed token, uint256 amount);
+    event SignedPayrollExecuted(address indexed employer, address indexed relayer, uint256 nonce);
+    event PayrollNonceInvalidated(address indexed employer, uint256 nonce);
+    event ScheduleCreated(uint256 indexed schedule_id, address indexed employer, address token, uint256 run_total, uint64 interval, uint64 next_run, uint256 tip);
+    event ScheduleExecuted(uint256 indexed schedule_id, address indexed keeper, uint256 amount, uint256 tip, uint64 next_run);
+    event ScheduleCancelled(uint256 indexed schedule_id);
+}
+
+/// Prints the pool events as a Solidity interface, next to the generated `print_abi`
+#[cfg(feature = "export-abi")]
+pub fn print_events() {
+    let events = EVENTS_ABI.split_whitespace().collect::<Vec<_>>().join(" ");
+    println!();
+    println!("interface IEmployerPoolEvents {{");
+    for event in events.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        println!("    {event};");
+    }
+    println!("}}");
 }
 
 #[derive(SolidityError)]
 pub enum EmployerPoolError {
     InsufficientBalance(InsufficientBalance),
+    TokenNotAllowed(TokenNotAllowed),
+    UnknownStream(UnknownStream),
+    StreamNotActive(StreamNotActive),
+    UnknownGrant(UnknownGrant),
+    GrantAlreadyRevoked(GrantAlreadyRevoked),
+    WorkerNotRegistered(WorkerNotRegistered),
+    WorkerAlreadyRegistered(WorkerAlreadyRegistered),
+    UnknownDistribution(UnknownDistribution),
+    DistributionExpired(DistributionExpired),
+    DistributionNotExpired(DistributionNotExpired),
+    AlreadyClaimed(AlreadyClaimed),
+    InvalidProof(InvalidProof),
+    InvalidRunId(InvalidRunId),
+    UnpayableWorker(UnpayableWorker),
+    NotOperator(NotOperator),
+    OperatorCapExceeded(OperatorCapExceeded),
+    AdminChangeNotReady(AdminChangeNotReady),
+    UnknownProposal(UnknownProposal),
+    ProposalClosed(ProposalClosed),
+    NotEnoughApprovals(NotEnoughApprovals),
+    GuardianChangeNotReady(GuardianChangeNotReady),
+    ActionPaused(ActionPaused),
+    NoWithdrawalRequest(NoWithdrawalRequest),
+    WithdrawalNotReady(WithdrawalNotReady),
+    FundsCommitted(FundsCommitted),
+    TokenBalanceMismatch(TokenBalanceMismatch),
+    UnexpectedTransferAmount(UnexpectedTransferAmount),
+    PayrollExpired(PayrollExpired),
+    InvalidPayrollNonce(InvalidPayrollNonce),
+    InvalidPayrollSigner(InvalidPayrollSigner),
+    InvalidSignature(InvalidSignature),
+    PayrollLengthMismatch(PayrollLengthMismatch),
+    UnknownSchedule(UnknownSchedule),
+    ScheduleNotActive(ScheduleNotActive),
+    ScheduleNotDue(ScheduleNotDue),
+    TokenCallFailed(TokenCallFailed),
+    TokenReturnedFalse(TokenReturnedFalse),
+    InvalidTokenReturn(InvalidTokenReturn),
+}
+
+impl From<AdminError> for EmployerPoolError {
+    fn from(err: AdminError) -> Self {
+        match err {
+            AdminError::AdminChangeNotReady(e) => EmployerPoolError::AdminChangeNotReady(e),
+        }
+    }
+}
+
+impl From<GuardianError> for EmployerPoolError {
+    fn from(err: GuardianError) -> Self {
+        match err {
+            GuardianError::UnknownProposal(e) => EmployerPoolError::UnknownProposal(e),
+            GuardianError::ProposalClosed(e) => EmployerPoolError::ProposalClosed(e),
+            GuardianError::NotEnoughApprovals(e) => EmployerPoolError::NotEnoughApprovals(e),
+            GuardianError::GuardianChangeNotReady(e) => EmployerPoolError::GuardianChangeNotReady(e),
+        }
+    }
+}
+
+impl From<TokenError> for EmployerPoolError {
+    fn from(err: TokenError) -> Self {
+        match err {
+            TokenError::TokenCallFailed(e) => EmployerPoolError::TokenCallFailed(e),
+            TokenError::TokenReturnedFalse(e) => EmployerPoolError::TokenReturnedFalse(e),
+            TokenError::InvalidTokenReturn(e) => EmployerPoolError::InvalidTokenReturn(e),
+        }
+    }
+}
+
+/// Per-worker outcome of a best effort payroll, returned as `uint8[]`
+#[repr(u8)]
+pub enum PayStatus {
+    Paid = 0,
+    SkippedInsufficientBalance = 1,
+    TransferFailed = 2,
 }
 
 #[public]
 impl EmployerPool {
     /// (Constructor) intialize the admin
     pub fn initialize_admin(&mut self) {
-        if Address::from(*self.admin.get()) == Address::default() {
-            self.admin.set(self.vm().msg_sender());
+        self.reentrancy.check();
+        let admin = self.vm().msg_sender();
+        if self.admin.initialize(admin) {
+            log(self.vm(), AdminChanged { previous_admin: Address::ZERO, new_admin: admin });
         }
     }
-    //intialize a token for now, multi token will be added for future. mock usdc only now
+    //sets the default token used by the single token functions, it is added to the allowlist too
     pub fn set_address(&mut self, _token: Address) {
+        self.reentrancy.check();
         assert_eq!(
             Address::from(*self.admin.get()),
             self.vm().msg_sender(),
             "Only admin can mutate this"
         );
+        let previous_token = self.token.get();
         self.token.set(_token);
+        Self::allow_token(self, _token);
+        log(self.vm(), TokenChanged { previous_token, new_token: _token });
     }
 
-    
-    pub fn change_admin(&mut self, new_admin: Address) {
+    //token allowlist, admin only
+    pub fn add_token(&mut self, token: Address) {
+        self.reentrancy.check();
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can mutate this"
+        );
+        Self::allow_token(self, token);
+    }
+
+    //stops new deposits in this token, balances already in the pool can still be paid out
+    pub fn remove_token(&mut self, token: Address) {
+        self.reentrancy.check();
         assert_eq!(
             Address::from(*self.admin.get()),
             self.vm().msg_sender(),
-            "Only admin can change admin"
+            "Only admin can mutate this"
         );
-        self.admin.set(new_admin);
+        if !self.allowed_tokens.get(token) {
+            return;
+        }
+        self.allowed_tokens.setter(token).set(false);
+        log(self.vm(), TokenAllowlisted { token, allowed: false });
+
+        let len = self.token_list.len();
+        for i in 0..len {
+            if self.token_list.get(i) == Some(token) {
+                let last = self.token_list.get(len - 1).unwrap();
+                self.token_list.setter(i).unwrap().set(last);
+                self.token_list.pop();
+                break;
+            }
+        }
+    }
+
+    pub fn is_token_allowed(&self, token: Address) -> bool {
+        self.allowed_tokens.get(token)
+    }
+
+    pub fn allowed_tokens(&self) -> Vec<Address> {
+        (0..self.token_list.len())
+            .filter_map(|i| self.token_list.get(i))
+            .collect()
+    }
+
+    pub fn default_token(&self) -> Address {
+        self.token.get()
+    }
+
+    //proposes new_admin, who has to call accept_admin once admin_delay has passed
+    pub fn change_admin(&mut self, new_admin: Address) {
+        self.reentrancy.check();
+        let eta = self.admin.propose(new_admin);
+        log(self.vm(), AdminChangeProposed { current_admin: self.admin.get(), pending_admin: new_admin, eta });
+    }
+
+    pub fn accept_admin(&mut self) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let previous_admin = self.admin.accept()?;
+        log(self.vm(), AdminChanged { previous_admin, new_admin: self.admin.get() });
+        Ok(())
+    }
+
+    pub fn cancel_admin_change(&mut self) {
+        self.reentrancy.check();
+        let pending_admin = self.admin.cancel();
+        log(self.vm(), AdminChangeCancelled { pending_admin });
+    }
+
+    //takes effect once the current delay has passed, then applies to admin changes proposed afterwards
+    pub fn set_admin_delay(&mut self, delay: u64) {
+        self.reentrancy.check();
+        let eta = self.admin.set_delay(delay);
+        log(self.vm(), AdminDelayChanged { delay, eta });
+    }
+
+    pub fn current_admin(&self) -> Address {
+        self.admin.get()
+    }
+
+    //(pending admin, time from which it can accept)
+    pub fn pending_admin(&self) -> (Address, u64) {
+        self.admin.pending()
+    }
+
+    pub fn admin_delay(&self) -> u64 {
+        self.admin.delay()
+    }
+
+    //(delay, time it takes effect), eta 0 when no change is scheduled
+    pub fn pending_admin_delay(&self) -> (u64, u64) {
+        self.admin.pending_delay()
     }
 
     //deposits after intialized address
 
-    pub fn deposit(&mut self, amount: U256) -> bool {
+    pub fn deposit(&mut self, amount: U256) -> Result<bool, EmployerPoolError> {
+        self.reentrancy.check();
+        let token = self.token.get();
+        Self::deposit_token(self, token, amount)
+    }
+
+    //credits what actually arrived, so fee on transfer tokens are credited net of the fee
+    //a pool balance below what the pool accounted for means the token rebased down, those deposits are rejected
+    pub fn deposit_token(&mut self, token: Address, amount: U256) -> Result<bool, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, DEPOSITS)?;
+        Self::only_allowed(self, token)?;
         let employer = self.vm().msg_sender();
-        let current = self.balances.get(employer);
+        let pool = self.vm().contract_address();
 
         // Check the external token balance of the employer.
-        let ext_balance = self.token_balance(employer);
+        let ext_balance = self.token_balance_of(token, employer)?;
         if ext_balance < amount {
-            return false;
+            return Ok(false);
         }
 
-        let success = Self::transfer_from_token(self, employer, contract::address(), amount);
-        
-        self.balances.setter(employer).set(current + amount);
-        success
-    }
-    //total pased from the fe 
-    pub fn pay_workers(&mut self, workers: Vec<(Address, U256)>, _total:U256) -> Result<bool, EmployerPoolError> {
-        let employer = self.vm().msg_sender();
-        let mut bal = Self::employer_balance(&self, employer);
+        let tracked = self.token_holdings.get(token);
+        let before = self.token_balance_of(token, pool)?;
+        if before < tracked {
+            return Err(EmployerPoolError::TokenBalanceMismatch(TokenBalanceMismatch {
+                token,
+                tracked,
+                actual: before,
+            }));
+        }
 
-        if _total > bal {
-            return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
-                balance: bal,
+        // the credit depends on the measured delta so it has to come after the call,
+        // the reentrancy lock held during transfer_from_token covers it
+        Self::transfer_from_token(self, token, employer, pool, amount)?;
+        let received = self.token_balance_of(token, pool)?.saturating_sub(before);
+        if received > amount {
+            return Err(EmployerPoolError::UnexpectedTransferAmount(UnexpectedTransferAmount {
+                token,
+                amount,
+                received,
             }));
         }
-        else{
-            for (worker_address, amount) in workers {
-                if bal < amount {
-                   continue;
-                }
-                let success = Self::transfer_token(self, worker_address, amount);
-                bal = bal - amount;
-                self.balances.setter(employer).set(bal);
-            }
+
+        self.credit(employer, token, received);
+        self.token_holdings.setter(token).set(tracked + received);
+        self.ledger.record_deposit(employer, token, received);
+        log(self.vm(), Deposited { employer, token, amount: received });
+        Ok(true)
+    }
+
+    //approve and deposit in one transaction with an EIP-2612 signature over (employer, pool, amount, nonce, deadline)
+    pub fn deposit_with_permit(&mut self, amount: U256, deadline: U256, v: u8, r: B256, s: B256) -> Result<bool, EmployerPoolError> {
+        self.reentrancy.check();
+        let token = self.token.get();
+        Self::deposit_token_with_permit(self, token, amount, deadline, v, r, s)
+    }
+
+    //a permit someone else already submitted is fine as long as the allowance is there
+    pub fn deposit_token_with_permit(
+        &mut self,
+        token: Address,
+        amount: U256,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<bool, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, DEPOSITS)?;
+        Self::only_allowed(self, token)?;
+        let employer = self.vm().msg_sender();
+        let pool = self.vm().contract_address();
+        self.reentrancy.lock();
+        safe_erc20::permit(token, employer, pool, amount, deadline, v, r, s)?;
+        self.reentrancy.unlock();
+        Self::deposit_token(self, token, amount)
+    }
+
+    //what the pool expects to hold in token, compare with token_balance_of(token, pool) to spot drift
+    pub fn tracked_token_balance(&self, token: Address) -> U256 {
+        self.token_holdings.get(token)
+    }
+
+    //after a token rebased down deposits revert with TokenBalanceMismatch, this lowers what the pool
+    //expects to hold to what it actually holds so they work again, returns the new tracked balance
+    //employer balances are left as they are, use remove_token for a token that keeps rebasing
+    pub fn sync_token_holdings(&mut self, token: Address) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can mutate this"
+        );
+        let tracked = self.token_holdings.get(token);
+        let actual = self.token_balance_of(token, self.vm().contract_address())?;
+        if actual >= tracked {
+            return Ok(tracked);
         }
-        
-        
-     Ok(true)
+        self.token_holdings.setter(token).set(actual);
+        log(self.vm(), TokenHoldingsSynced { token, tracked, actual });
+        Ok(actual)
+    }
+    //total pased from the fe 
+    //best effort, returns one PayStatus per worker in the same order
+    pub fn pay_workers(&mut self, workers: Vec<(Address, U256)>, _total:U256) -> Result<Vec<u8>, EmployerPoolError> {
+        self.reentrancy.check();
+        let token = self.token.get();
+        Self::pay_workers_token(self, token, workers, _total)
+    }
+
+    pub fn pay_workers_token(&mut self, token: Address, workers: Vec<(Address, U256)>, _total:U256) -> Result<Vec<u8>, EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        Self::pay_from(self, employer, token, workers, _total)
+    }
+
+    //all or nothing, reverts with UnpayableWorker on the first worker that cannot be paid
+    pub fn pay_workers_atomic(&mut self, token: Address, workers: Vec<(Address, U256)>) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        Self::pay_all_from(self, employer, token, workers)
     }
     //employer address will be passed, how much they own in the pool
-    //admin only admin can call
+    //only an operator the employer granted for this token can call, see grant_operator
+    //for a one-off run the employer can sign it instead, see pay_workers_signed
     //fe checks if the total amount is enough before calling this function
-    pub fn auto_pay_workers(&mut self, employer:Address ,workers: Vec<(Address, U256)>, _total:U256) -> Result<bool, EmployerPoolError> {
-        let mut bal = Self::employer_balance(&self, employer);
+    pub fn auto_pay_workers(&mut self, employer:Address ,workers: Vec<(Address, U256)>, _total:U256) -> Result<Vec<u8>, EmployerPoolError> {
+        self.reentrancy.check();
+        let token = self.token.get();
+        Self::auto_pay_workers_token(self, token, employer, workers, _total)
+    }
 
-        if _total > bal {
-            return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
-                balance: bal,
+    pub fn auto_pay_workers_token(&mut self, token: Address, employer:Address ,workers: Vec<(Address, U256)>, _total:U256) -> Result<Vec<u8>, EmployerPoolError> {
+        self.reentrancy.check();
+        let operator = self.vm().msg_sender();
+        Self::check_operator(self, employer, operator, token, &workers)?;
+
+        let statuses = Self::pay_from(self, employer, token, workers.clone(), _total)?;
+        let paid = workers
+            .into_iter()
+            .zip(statuses.iter())
+            .filter(|(_, status)| **status == PayStatus::Paid as u8)
+            .map(|(worker, _)| worker)
+            .collect();
+        Self::record_operator_payments(self, employer, operator, token, paid);
+        Ok(statuses)
+    }
+
+    pub fn auto_pay_workers_atomic(&mut self, token: Address, employer:Address ,workers: Vec<(Address, U256)>) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let operator = self.vm().msg_sender();
+        Self::check_operator(self, employer, operator, token, &workers)?;
+
+        Self::pay_all_from(self, employer, token, workers.clone())?;
+        Self::record_operator_payments(self, employer, operator, token, workers);
+        Ok(())
+    }
+
+    //runs a payroll the employer signed off-chain, any relayer can submit it
+    //the signature covers (employer, token, workers, amounts, nonce, deadline) under payroll_domain_separator
+    //best effort like pay_workers, the nonce is used up even if some workers are skipped
+    #[allow(clippy::too_many_arguments)]
+    pub fn pay_workers_signed(
+        &mut self,
+        employer: Address,
+        token: Address,
+        workers: Vec<Address>,
+        amounts: Vec<U256>,
+        nonce: U256,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<Vec<u8>, EmployerPoolError> {
+        self.reentrancy.check();
+        if workers.len() != amounts.len() {
+            return Err(EmployerPoolError::PayrollLengthMismatch(PayrollLengthMismatch {
+                workers: U256::from(workers.len()),
+                amounts: U256::from(amounts.len()),
             }));
         }
+        if U256::from(self.vm().block_timestamp()) > deadline {
+            return Err(EmployerPoolError::PayrollExpired(PayrollExpired { deadline }));
+        }
+        let expected = self.payroll_nonces.get(employer);
+        if nonce != expected {
+            return Err(EmployerPoolError::InvalidPayrollNonce(InvalidPayrollNonce { expected, nonce }));
+        }
 
-        assert_eq!(
-            Address::from(*self.admin.get()),
-            employer,
-            "Only admin can auto pay workers!"
-        );
-
-        for (worker_address, amount) in workers {
-            if bal < amount {
-                continue;
-            }
-            let success = Self::transfer_token(self, worker_address, amount);
-            bal = bal - amount;
-            self.balances.setter(employer).set(bal);
+        let payroll = Payroll { employer, token, workers, amounts, nonce, deadline };
+        let Some(signer) = eip712::signer(&payroll, &self.payroll_domain(), v, r, s) else {
+            return Err(EmployerPoolError::InvalidSignature(InvalidSignature {}));
+        };
+        if signer != employer {
+            return Err(EmployerPoolError::InvalidPayrollSigner(InvalidPayrollSigner { signer, employer }));
         }
-        Ok(true)
+        self.payroll_nonces.setter(employer).set(nonce + U256::from(1));
+
+        let total = payroll.amounts.iter().fold(U256::ZERO, |sum, amount| sum.saturating_add(*amount));
+        let workers = payroll.workers.into_iter().zip(payroll.amounts).collect();
+        let statuses = Self::pay_from(self, employer, payroll.token, workers, total)?;
+        log(self.vm(), SignedPayrollExecuted { employer, relayer: self.vm().msg_sender(), nonce });
+        Ok(statuses)
+    }
+
+    //nonce the employer's next signed payroll has to use
+    pub fn payroll_nonce(&self, employer: Address) -> U256 {
+        self.payroll_nonces.get(employer)
+    }
+
+    //skips the caller's current nonce, so a signed payroll that was not relayed yet can no longer be
+    pub fn invalidate_payroll_nonce(&mut self) {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let nonce = self.payroll_nonces.get(employer);
+        self.payroll_nonces.setter(employer).set(nonce + U256::from(1));
+        log(self.vm(), PayrollNonceInvalidated { employer, nonce });
+    }
+
+    //EIP-712 domain signed payrolls are signed under, name "EmployerPool" and version "1"
+    pub fn payroll_domain_separator(&self) -> B256 {
+        self.payroll_domain().separator()
+    }
+
+    //lets operator run auto_pay_workers from the caller's balance in token
+    //cap 0 is unlimited, cap_period 0 applies the cap over the whole grant, expiry 0 never expires
+    pub fn grant_operator(
+        &mut self,
+        operator: Address,
+        token: Address,
+        cap: U256,
+        cap_period: u64,
+        expiry: u64,
+    ) {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+
+        let mut operators = self.operators.setter(employer);
+        let mut by_token = operators.setter(operator);
+        let mut grant = by_token.setter(token);
+        grant.cap.set(cap);
+        grant.cap_period.set(U64::from(cap_period));
+        grant.period_start.set(U64::from(now));
+        grant.spent.set(U256::ZERO);
+        grant.expiry.set(U64::from(expiry));
+        grant.active.set(true);
+        log(self.vm(), OperatorGranted { employer, operator, token, cap, cap_period, expiry });
+    }
+
+    pub fn revoke_operator(&mut self, operator: Address, token: Address) {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let mut operators = self.operators.setter(employer);
+        let mut by_token = operators.setter(operator);
+        by_token.setter(token).active.set(false);
+        log(self.vm(), OperatorRevoked { employer, operator, token });
+    }
+
+    //(valid, cap, cap_period, expiry, remaining in the current period)
+    pub fn operator_info(
+        &self,
+        employer: Address,
+        operator: Address,
+        token: Address,
+    ) -> (bool, U256, u64, u64, U256) {
+        let now = self.vm().block_timestamp();
+        let operators = self.operators.getter(employer);
+        let by_token = operators.getter(operator);
+        let grant = by_token.getter(token);
+        let valid = grant.is_valid(now);
+        (
+            valid,
+            grant.cap.get(),
+            grant.cap_period.get().to::<u64>(),
+            grant.expiry.get().to::<u64>(),
+            if valid { grant.remaining(now) } else { U256::ZERO },
+        )
     }
 
     pub fn transfer_by_employer(&mut self, _recipient:Address ,_amount : U256) -> Result<bool, EmployerPoolError> {
+        self.reentrancy.check();
+        let token = self.token.get();
+        Self::transfer_by_employer_token(self, token, _recipient, _amount)
+    }
+
+    pub fn transfer_by_employer_token(&mut self, token: Address, _recipient:Address ,_amount : U256) -> Result<bool, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
         let employer = self.vm().msg_sender();
-        let mut bal = Self::employer_balance(&self, employer);
+        assert_ne!(_recipient, employer, "Use request_withdrawal to take funds back");
+        let mut bal = Self::employer_token_balance(self, employer, token);
+
+        let available = self.available_balance(employer, token);
 
         if _amount > bal {
             return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
                 balance: bal,
             }));
+        } else if _amount > available {
+            return Err(EmployerPoolError::FundsCommitted(FundsCommitted { available }));
         } else {
-            Self::transfer_token(self, _recipient, _amount);
-            bal = bal - _amount;
-            self.balances.setter(employer).set(bal);
+            bal -= _amount;
+            self.balances.setter(employer).setter(token).set(bal);
+            Self::transfer_token(self, token, _recipient, _amount)?;
+            log(self.vm(), EmployerTransfer { employer, recipient: _recipient, token, amount: _amount });
         }
         Ok(true)
     }
 
+    //taking unused funds back goes through a notice period so workers see it coming
+    //salary due or being earned stays locked, see available_balance
+    pub fn set_withdrawal_notice(&mut self, notice: u64) {
+        self.reentrancy.check();
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can mutate this"
+        );
+        self.withdrawal_notice.set(U64::from(notice));
+        log(self.vm(), WithdrawalNoticeChanged { notice });
+    }
+
+    pub fn withdrawal_notice(&self) -> u64 {
+        self.withdrawal_notice.get().to::<u64>()
+    }
+
+    //replaces any earlier request for the same token, returns when it can be executed
+    pub fn request_withdrawal(&mut self, token: Address, amount: U256) -> Result<u64, EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let available = self.available_balance(employer, token);
+        if amount > available {
+            return Err(EmployerPoolError::FundsCommitted(FundsCommitted { available }));
+        }
+
+        let eta = self.vm().block_timestamp() + self.withdrawal_notice.get().to::<u64>();
+        let mut requests = self.withdrawal_requests.setter(employer);
+        let mut request = requests.setter(token);
+        request.amount.set(amount);
+        request.eta.set(U64::from(eta));
+        log(self.vm(), WithdrawalRequested { employer, token, amount, eta });
+        Ok(eta)
+    }
+
+    pub fn cancel_withdrawal(&mut self, token: Address) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        if self.withdrawal_request(employer, token).1 == 0 {
+            return Err(EmployerPoolError::NoWithdrawalRequest(NoWithdrawalRequest { employer, token }));
+        }
+        let mut requests = self.withdrawal_requests.setter(employer);
+        let mut request = requests.setter(token);
+        request.amount.set(U256::ZERO);
+        request.eta.set(U64::ZERO);
+        log(self.vm(), WithdrawalRequestCancelled { employer, token });
+        Ok(())
+    }
+
+    //pays the requested amount back to the employer, as long as it is still not committed
+    pub fn execute_withdrawal(&mut self, token: Address) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
+        let employer = self.vm().msg_sender();
+        let (amount, eta) = self.withdrawal_request(employer, token);
+        if eta == 0 {
+            return Err(EmployerPoolError::NoWithdrawalRequest(NoWithdrawalRequest { employer, token }));
+        }
+        if self.vm().block_timestamp() < eta {
+            return Err(EmployerPoolError::WithdrawalNotReady(WithdrawalNotReady { eta }));
+        }
+        let available = self.available_balance(employer, token);
+        if amount > available {
+            return Err(EmployerPoolError::FundsCommitted(FundsCommitted { available }));
+        }
+
+        let mut requests = self.withdrawal_requests.setter(employer);
+        let mut request = requests.setter(token);
+        request.amount.set(U256::ZERO);
+        request.eta.set(U64::ZERO);
+        self.debit(employer, token, amount)?;
+        Self::transfer_token(self, token, employer, amount)?;
+        log(self.vm(), EmployerWithdrawal { employer, token, amount });
+        Ok(amount)
+    }
+
+    //(amount, time from which it can be executed), eta 0 when nothing is requested
+    pub fn withdrawal_request(&self, employer: Address, token: Address) -> (U256, u64) {
+        let requests = self.withdrawal_requests.getter(employer);
+        let request = requests.getter(token);
+        (request.amount.get(), request.eta.get().to::<u64>())
+    }
+
+    //salary due or being earned by the employer's registered workers paid in token,
+    //plus the due or next run of each of their schedules in token
+    //streams, grants and distributions are escrowed out of the balance already
+    pub fn committed_balance(&self, employer: Address, token: Address) -> U256 {
+        let now = self.vm().block_timestamp();
+        let registered = self.workers.getter(employer);
+        let salaries = self.employer_workers(employer)
+            .into_iter()
+            .map(|worker| registered.getter(worker))
+            .filter(|entry| entry.token.get() == token)
+            .fold(U256::ZERO, |sum, entry| sum.saturating_add(entry.committed_at(now)));
+        self.employer_schedules(employer)
+            .into_iter()
+            .filter_map(|id| self.schedules.getter(id))
+            .filter(|schedule| schedule.token.get() == token)
+            .fold(salaries, |sum, schedule| sum.saturating_add(schedule.committed_at(now)))
+    }
+
+    //what the employer can withdraw or transfer away
+    pub fn available_balance(&self, employer: Address, token: Address) -> U256 {
+        let bal = self.employer_token_balance(employer, token);
+        bal.saturating_sub(self.committed_balance(employer, token))
+    }
+
     pub fn employer_balance(&self, employer: Address) -> U256 {
-        self.balances.get(employer)
+        self.balances.getter(employer).get(self.token.get())
+    }
+
+    pub fn employer_token_balance(&self, employer: Address, token: Address) -> U256 {
+        self.balances.getter(employer).get(token)
     }
 
     pub fn my_balance(&self) -> U256 {
-        self.balances.get(self.vm().msg_sender())
+        self.balances.getter(self.vm().msg_sender()).get(self.token.get())
+    }
+
+    pub fn my_token_balance(&self, token: Address) -> U256 {
+        self.balances.getter(self.vm().msg_sender()).get(token)
+    }
+
+    pub fn token_balance(&self, owner: Address) -> Result<U256, EmployerPoolError> {
+        self.token_balance_of(self.token.get(), owner)
+    }
+
+    //reverts when the token call fails instead of reporting 0
+    pub fn token_balance_of(&self, token: Address, owner: Address) -> Result<U256, EmployerPoolError> {
+        Ok(safe_erc20::balance_of(token, owner)?)
     }
 
-    pub fn token_balance(&self, owner: Address) -> U256 {
-        let result = RawCall::new_static().call(
-            alloy_primitives::Address(*self.token.get()),
-            &balanceOfCall { account: owner }.abi_encode(),
+    //in case of emergency... most esp lost wallet
+    //withdrawals are guardian proposals that need threshold approvals before anyone can execute them
+    //the new set only takes effect admin_delay, and at least guardian::MIN_SET_DELAY, after it was proposed
+    //see apply_guardians
+    pub fn set_guardians(&mut self, guardians: Vec<Address>, threshold: U256) {
+        self.reentrancy.check();
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can mutate this"
         );
+        let eta = self.guardians.propose_set(guardians.clone(), threshold, self.admin.delay());
+        log(self.vm(), GuardiansChangeProposed { guardians, threshold, eta });
+    }
 
-        match result {
-            Ok(data) => U256::from_be_bytes::<32>(data.try_into().unwrap_or([0u8; 32])),
-            Err(_) => U256::from(0), // Returns 0 if the call fails
-        }
+    //anyone can apply the proposed set once its delay has passed
+    pub fn apply_guardians(&mut self) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let (guardians, threshold) = self.guardians.apply_set()?;
+        log(self.vm(), GuardiansChanged { guardians, threshold });
+        Ok(())
     }
 
-    pub fn emergency_withdraw(&mut self, employer_addres:Address ,_amount:U256) -> Result<(), EmployerPoolError> {
-        //in case of emergency... most esp lost wallet
-        //money glitch found. FUCK!
+    pub fn cancel_guardians_change(&mut self) {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        assert!(
+            sender == self.admin.get() || self.guardians.is_member(sender),
+            "Only admin or a guardian can cancel"
+        );
+        self.guardians.cancel_set();
+        log(self.vm(), GuardiansChangeCancelled {});
+    }
 
-        let mut bal = Self::employer_balance(&self, employer_addres);
+    //(guardians, threshold, time from which it can be applied), eta 0 when nothing is proposed
+    pub fn pending_guardians(&self) -> (Vec<Address>, U256, u64) {
+        self.guardians.pending_set()
+    }
 
-        if _amount > bal {
-            return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
-                balance: bal,
-            }));
+    //(guardians, threshold)
+    pub fn guardians(&self) -> (Vec<Address>, U256) {
+        (self.guardians.members(), self.guardians.threshold())
+    }
+
+    pub fn propose_emergency_withdraw(
+        &mut self,
+        token: Address,
+        employer: Address,
+        amount: U256,
+        destination: Address,
+        expiry: u64,
+    ) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let bal = self.employer_token_balance(employer, token);
+        if amount > bal {
+            return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance { balance: bal }));
         }
 
-        let admin : Address = self.admin.get();
+        let proposal_id = self.guardians.propose(employer, token, amount, destination, expiry);
+        log(self.vm(), WithdrawalProposed { proposal_id, employer, token, amount, destination, expiry });
+        Ok(proposal_id)
+    }
+
+    pub fn approve_emergency_withdraw(&mut self, proposal_id: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let approvals = self.guardians.approve(proposal_id)?;
+        log(self.vm(), WithdrawalApproved { proposal_id, guardian: self.vm().msg_sender(), approvals });
+        Ok(())
+    }
+
+    pub fn cancel_emergency_withdraw(&mut self, proposal_id: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        assert!(
+            sender == self.admin.get() || self.guardians.is_member(sender),
+            "Only admin or a guardian can cancel"
+        );
+        self.guardians.cancel(proposal_id)?;
+        log(self.vm(), WithdrawalCancelled { proposal_id });
+        Ok(())
+    }
+
+    //anyone can execute once enough guardians approved
+    pub fn execute_emergency_withdraw(&mut self, proposal_id: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let withdrawal = self.guardians.execute(proposal_id)?;
+        let employer = withdrawal.employer;
+        let token = withdrawal.token;
+        let amount = withdrawal.amount;
+        let destination = withdrawal.destination;
+
+        self.debit_available(employer, token, amount)?;
+        Self::transfer_token(self, token, destination, amount)?;
+        log(self.vm(), EmergencyWithdrawal { employer, to: destination, token, amount });
+        Ok(())
+    }
+
+    //(employer, token, amount, destination, expiry, approvals, open)
+    #[allow(clippy::type_complexity)]
+    pub fn emergency_withdraw_proposal(
+        &self,
+        proposal_id: U256,
+    ) -> Result<(Address, Address, U256, Address, u64, U256, bool), EmployerPoolError> {
+        Ok(self.guardians.proposal(proposal_id)?)
+    }
+
+    //circuit breaker, the pauser is separate from the admin so it can be a hot key or a monitor
+    //guardian withdrawals and views keep working while paused
+    pub fn set_pauser(&mut self, pauser: Address) {
+        self.reentrancy.check();
         assert_eq!(
             Address::from(*self.admin.get()),
             self.vm().msg_sender(),
-            "Only admin can call this booga ooga restrictive function"
+            "Only admin can mutate this"
         );
+        let previous_pauser = self.pausable.set_pauser(pauser);
+        log(self.vm(), PauserChanged { previous_pauser, new_pauser: pauser });
+    }
 
-        Self::transfer_token(self, admin, _amount);
-        bal = bal - _amount;
-        self.balances.setter(employer_addres).set(bal);
-        Ok(())
+    //actions is a bit set of pausable::DEPOSITS and pausable::PAYOUTS, until 0 pauses until unpause
+    pub fn pause(&mut self, actions: u8, until: u64) {
+        self.reentrancy.check();
+        self.pausable.pause(actions, until);
+        log(self.vm(), Paused { pauser: self.vm().msg_sender(), actions, until });
     }
 
-    fn balance(&self) -> U256{
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-        token.balance()
+    pub fn unpause(&mut self, actions: u8) {
+        self.reentrancy.check();
+        self.pausable.unpause(actions);
+        log(self.vm(), Unpaused { pauser: self.vm().msg_sender(), actions });
     }
-}
 
-impl EmployerPool {
+    pub fn pauser(&self) -> Address {
+        self.pausable.pauser()
+    }
 
-    //internal func
+    //(paused actions, time they resume on their own or 0)
+    pub fn paused(&self) -> (u8, u64) {
+        (self.pausable.paused_actions(), self.pausable.paused_until())
+    }
 
-    fn transfer_from_token(&mut self, from: Address, to: Address, amount: U256) -> bool {
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
+    //salary streams, funded from the employer's pool balance
+    //start_time 0 starts now, stop_time 0 streams until cancelled or out of funds
+    pub fn create_stream(
+        &mut self,
+        token: Address,
+        worker: Address,
+        rate_per_second: U256,
+        start_time: u64,
+        stop_time: u64,
+        amount: U256,
+    ) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let start = if start_time == 0 { now } else { start_time };
+        assert!(rate_per_second > U256::ZERO, "Rate must be above zero");
+        assert!(stop_time == 0 || stop_time > start, "Stop must be after start");
+
+        self.debit_available(employer, token, amount)?;
 
-        token
-            .transfer_from(self, from, to, amount)
-            .expect("approve token first")
+        let id = U256::from(self.streams.len());
+        let mut stream = self.streams.grow();
+        stream.employer.set(employer);
+        stream.worker.set(worker);
+        stream.token.set(token);
+        stream.rate.set(rate_per_second);
+        stream.start.set(U64::from(start));
+        stream.stop.set(U64::from(stop_time));
+        stream.deposit.set(amount);
+        stream.checkpoint_time.set(U64::from(start));
+        stream.active.set(true);
+
+        self.employer_streams.setter(employer).push(id);
+        self.worker_streams.setter(worker).push(id);
+        log(self.vm(), StreamCreated { stream_id: id, employer, worker, token, rate: rate_per_second, amount });
+        Ok(id)
+    }
+
+    //worker pulls whatever has accrued so far, also works after the stream is cancelled
+    pub fn withdraw_from_stream(&mut self, stream_id: U256) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
+        let now = self.vm().block_timestamp();
+        let sender = self.vm().msg_sender();
+        let mut stream = self.stream_mut(stream_id)?;
+        assert_eq!(stream.worker.get(), sender, "Only the stream worker can withdraw");
+
+        let amount = stream.withdrawable_at(now);
+        let withdrawn = stream.withdrawn.get() + amount;
+        stream.withdrawn.set(withdrawn);
+        let token = stream.token.get();
+        let employer = stream.employer.get();
+
+        if amount > U256::ZERO {
+            let run_id = self.ledger.start_run();
+            self.ledger.record_payment(run_id, now, employer, sender, token, amount);
+            Self::transfer_token(self, token, sender, amount)?;
+            log(self.vm(), StreamWithdrawn { stream_id, worker: sender, amount });
+        }
+        Ok(amount)
+    }
+
+    pub fn pause_stream(&mut self, stream_id: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let now = self.vm().block_timestamp();
+        let sender = self.vm().msg_sender();
+        let mut stream = self.active_stream_mut(stream_id)?;
+        assert_eq!(stream.employer.get(), sender, "Only the stream employer can pause");
+
+        stream.checkpoint(now);
+        stream.paused.set(true);
+        log(self.vm(), StreamPaused { stream_id });
+        Ok(())
+    }
+
+    pub fn resume_stream(&mut self, stream_id: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let now = self.vm().block_timestamp();
+        let sender = self.vm().msg_sender();
+        let mut stream = self.active_stream_mut(stream_id)?;
+        assert_eq!(stream.employer.get(), sender, "Only the stream employer can resume");
+
+        if stream.paused.get() {
+            if now > stream.checkpoint_time.get().to::<u64>() {
+                stream.checkpoint_time.set(U64::from(now));
+            }
+            stream.paused.set(false);
+            log(self.vm(), StreamResumed { stream_id });
+        }
+        Ok(())
+    }
+
+    //time the stream spent out of funds is not paid back by a top up
+    pub fn top_up_stream(&mut self, stream_id: U256, amount: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let now = self.vm().block_timestamp();
+        let employer = self.vm().msg_sender();
+        let stream = self.active_stream_mut(stream_id)?;
+        assert_eq!(stream.employer.get(), employer, "Only the stream employer can top up");
+        let token = stream.token.get();
+
+        self.debit_available(employer, token, amount)?;
+
+        let mut stream = self.active_stream_mut(stream_id)?;
+        stream.checkpoint(now);
+        let deposit = stream.deposit.get() + amount;
+        stream.deposit.set(deposit);
+        log(self.vm(), StreamToppedUp { stream_id, amount });
+        Ok(())
+    }
+
+    //accrued amount stays withdrawable by the worker, the rest goes back to the employer balance
+    pub fn cancel_stream(&mut self, stream_id: U256) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let now = self.vm().block_timestamp();
+        let employer = self.vm().msg_sender();
+        let mut stream = self.active_stream_mut(stream_id)?;
+        assert_eq!(stream.employer.get(), employer, "Only the stream employer can cancel");
+
+        stream.checkpoint(now);
+        let accrued = stream.accrued.get();
+        let refund = stream.deposit.get() - accrued;
+        stream.deposit.set(accrued);
+        stream.paused.set(true);
+        stream.active.set(false);
+        let token = stream.token.get();
+
+        self.credit(employer, token, refund);
+        log(self.vm(), StreamCancelled { stream_id, refund });
+        Ok(refund)
+    }
+
+    //(employer, worker, token, rate, start, stop, deposit, withdrawn, paused, active)
+    #[allow(clippy::type_complexity)]
+    pub fn stream_info(
+        &self,
+        stream_id: U256,
+    ) -> Result<(Address, Address, Address, U256, u64, u64, U256, U256, bool, bool), EmployerPoolError> {
+        let stream = self.stream(stream_id)?;
+        Ok((
+            stream.employer.get(),
+            stream.worker.get(),
+            stream.token.get(),
+            stream.rate.get(),
+            stream.start.get().to::<u64>(),
+            stream.stop.get().to::<u64>(),
+            stream.deposit.get(),
+            stream.withdrawn.get(),
+            stream.paused.get(),
+            stream.active.get(),
+        ))
+    }
+
+    pub fn stream_withdrawable(&self, stream_id: U256) -> Result<U256, EmployerPoolError> {
+        let now = self.vm().block_timestamp();
+        Ok(self.stream(stream_id)?.withdrawable_at(now))
+    }
+
+    pub fn employer_streams(&self, employer: Address) -> Vec<U256> {
+        let ids = self.employer_streams.getter(employer);
+        (0..ids.len()).filter_map(|i| ids.get(i)).collect()
+    }
+
+    pub fn worker_streams(&self, worker: Address) -> Vec<U256> {
+        let ids = self.worker_streams.getter(worker);
+        (0..ids.len()).filter_map(|i| ids.get(i)).collect()
+    }
+
+    //vesting grants for bonuses, locked from the employer's pool balance
+    //start_time 0 starts now, step_seconds 0 vests linearly
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_grant(
+        &mut self,
+        token: Address,
+        worker: Address,
+        amount: U256,
+        start_time: u64,
+        cliff_seconds: u64,
+        duration_seconds: u64,
+        step_seconds: u64,
+    ) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let start = if start_time == 0 { now } else { start_time };
+        assert!(duration_seconds > 0, "Duration must be above zero");
+        assert!(cliff_seconds <= duration_seconds, "Cliff longer than duration");
+        assert!(step_seconds <= duration_seconds, "Step longer than duration");
+
+        self.debit_available(employer, token, amount)?;
+
+        let id = U256::from(self.grants.len());
+        let mut grant = self.grants.grow();
+        grant.employer.set(employer);
+        grant.worker.set(worker);
+        grant.token.set(token);
+        grant.total.set(amount);
+        grant.start.set(U64::from(start));
+        grant.cliff.set(U64::from(cliff_seconds));
+        grant.duration.set(U64::from(duration_seconds));
+        grant.step.set(U64::from(step_seconds));
+
+        self.employer_grants.setter(employer).push(id);
+        self.worker_grants.setter(worker).push(id);
+        log(self.vm(), GrantCreated { grant_id: id, employer, worker, token, amount });
+        Ok(id)
+    }
+
+    pub fn claim_grant(&mut self, grant_id: U256) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
+        let now = self.vm().block_timestamp();
+        let sender = self.vm().msg_sender();
+        let mut grant = self.grant_mut(grant_id)?;
+        assert_eq!(grant.worker.get(), sender, "Only the grant worker can claim");
+
+        let amount = grant.claimable_at(now);
+        let claimed = grant.claimed.get() + amount;
+        grant.claimed.set(claimed);
+        let token = grant.token.get();
+        let employer = grant.employer.get();
+
+        if amount > U256::ZERO {
+            let run_id = self.ledger.start_run();
+            self.ledger.record_payment(run_id, now, employer, sender, token, amount);
+            Self::transfer_token(self, token, sender, amount)?;
+            log(self.vm(), GrantClaimed { grant_id, worker: sender, amount });
+        }
+        Ok(amount)
+    }
+
+    //vested part stays claimable by the worker, the locked part goes back to the employer balance
+    pub fn revoke_grant(&mut self, grant_id: U256) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let now = self.vm().block_timestamp();
+        let employer = self.vm().msg_sender();
+        let mut grant = self.grant_mut(grant_id)?;
+        assert_eq!(grant.employer.get(), employer, "Only the grant employer can revoke");
+        if grant.revoked.get() {
+            return Err(EmployerPoolError::GrantAlreadyRevoked(GrantAlreadyRevoked { grant_id }));
+        }
+
+        let vested = grant.vested_at(now);
+        let refund = grant.total.get() - vested;
+        grant.total.set(vested);
+        grant.revoked.set(true);
+        let token = grant.token.get();
+
+        self.credit(employer, token, refund);
+        log(self.vm(), GrantRevoked { grant_id, refund });
+        Ok(refund)
+    }
+
+    //(employer, worker, token, start, cliff, duration, step, revoked)
+    #[allow(clippy::type_complexity)]
+    pub fn grant_info(
+        &self,
+        grant_id: U256,
+    ) -> Result<(Address, Address, Address, u64, u64, u64, u64, bool), EmployerPoolError> {
+        let grant = self.grant(grant_id)?;
+        Ok((
+            grant.employer.get(),
+            grant.worker.get(),
+            grant.token.get(),
+            grant.start.get().to::<u64>(),
+            grant.cliff.get().to::<u64>(),
+            grant.duration.get().to::<u64>(),
+            grant.step.get().to::<u64>(),
+            grant.revoked.get(),
+        ))
+    }
+
+    //(vested, claimed, locked)
+    pub fn grant_amounts(&self, grant_id: U256) -> Result<(U256, U256, U256), EmployerPoolError> {
+        let now = self.vm().block_timestamp();
+        let grant = self.grant(grant_id)?;
+        let vested = grant.vested_at(now);
+        Ok((vested, grant.claimed.get(), grant.total.get() - vested))
+    }
+
+    pub fn employer_grants(&self, employer: Address) -> Vec<U256> {
+        let ids = self.employer_grants.getter(employer);
+        (0..ids.len()).filter_map(|i| ids.get(i)).collect()
+    }
+
+    pub fn worker_grants(&self, worker: Address) -> Vec<U256> {
+        let ids = self.worker_grants.getter(worker);
+        (0..ids.len()).filter_map(|i| ids.get(i)).collect()
+    }
+
+    //worker registry, the first salary is due one pay period after registering
+    pub fn register_worker(
+        &mut self,
+        worker: Address,
+        token: Address,
+        salary: U256,
+        pay_period: u64,
+    ) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        assert!(pay_period > 0, "Pay period must be above zero");
+        Self::only_allowed(self, token)?;
+
+        let mut registered = self.workers.setter(employer);
+        let mut entry = registered.setter(worker);
+        if entry.active.get() {
+            return Err(EmployerPoolError::WorkerAlreadyRegistered(WorkerAlreadyRegistered { worker }));
+        }
+        entry.token.set(token);
+        entry.salary.set(salary);
+        entry.pay_period.set(U64::from(pay_period));
+        entry.last_paid.set(U64::from(now));
+        entry.active.set(true);
+
+        self.rosters.setter(employer).push(worker);
+        log(self.vm(), WorkerRegistered { employer, worker, token, salary, pay_period });
+        Ok(())
+    }
+
+    //keeps the worker's pay schedule, nothing may be due so periods already worked keep their old terms
+    //the period in progress is paid on the new terms
+    pub fn update_worker(
+        &mut self,
+        worker: Address,
+        token: Address,
+        salary: U256,
+        pay_period: u64,
+    ) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        assert!(pay_period > 0, "Pay period must be above zero");
+        Self::only_allowed(self, token)?;
+
+        let mut registered = self.workers.setter(employer);
+        let mut entry = registered.setter(worker);
+        if !entry.active.get() {
+            return Err(EmployerPoolError::WorkerNotRegistered(WorkerNotRegistered { worker }));
+        }
+        assert!(entry.amount_due(now) == U256::ZERO, "Worker has salary due, run payroll first");
+        entry.token.set(token);
+        entry.salary.set(salary);
+        entry.pay_period.set(U64::from(pay_period));
+        log(self.vm(), WorkerUpdated { employer, worker, token, salary, pay_period });
+        Ok(())
+    }
+
+    //a partially elapsed pay period is not paid out
+    pub fn remove_worker(&mut self, worker: Address) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+
+        let mut registered = self.workers.setter(employer);
+        let mut entry = registered.setter(worker);
+        if !entry.active.get() {
+            return Err(EmployerPoolError::WorkerNotRegistered(WorkerNotRegistered { worker }));
+        }
+        assert!(entry.amount_due(now) == U256::ZERO, "Worker has salary due, run payroll first");
+        entry.active.set(false);
+
+        let mut roster = self.rosters.setter(employer);
+        let len = roster.len();
+        for i in 0..len {
+            if roster.get(i) == Some(worker) {
+                let last = roster.get(len - 1).unwrap();
+                roster.setter(i).unwrap().set(last);
+                roster.pop();
+                break;
+            }
+        }
+        log(self.vm(), WorkerRemoved { employer, worker });
+        Ok(())
+    }
+
+    //pays every registered worker who is due from the caller's balance
+    //workers the employer cannot cover, or whose transfer fails, are skipped and stay due
+    //returns (workers paid, workers skipped), workers can be paid in different tokens so there is no single total
+    pub fn run_payroll(&mut self) -> Result<(u64, u64), EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
+        let employer = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let mut workers_paid = 0u64;
+        let mut workers_skipped = 0u64;
+        let run_id = self.ledger.start_run();
+
+        for worker in self.employer_workers(employer) {
+            let registered = self.workers.getter(employer);
+            let entry = registered.getter(worker);
+            let periods = entry.periods_due(now);
+            let amount = entry.amount_due(now);
+            let token = entry.token.get();
+            let last_paid = entry.last_paid.get().to::<u64>();
+            let pay_period = entry.pay_period.get().to::<u64>();
+            if periods == 0 {
+                continue;
+            }
+            if self.debit(employer, token, amount).is_err() {
+                workers_skipped += 1;
+                continue;
+            }
+            self.workers
+                .setter(employer)
+                .setter(worker)
+                .last_paid
+                .set(U64::from(last_paid + periods * pay_period));
+            if !self.pay_worker(run_id, employer, token, worker, amount) {
+                self.credit(employer, token, amount);
+                self.workers.setter(employer).setter(worker).last_paid.set(U64::from(last_paid));
+                workers_skipped += 1;
+                continue;
+            }
+            workers_paid += 1;
+        }
+        log(self.vm(), PayrollExecuted {
+            employer,
+            workers_paid: U256::from(workers_paid),
+            workers_skipped: U256::from(workers_skipped),
+        });
+        Ok((workers_paid, workers_skipped))
+    }
+
+    pub fn employer_workers(&self, employer: Address) -> Vec<Address> {
+        let roster = self.rosters.getter(employer);
+        (0..roster.len()).filter_map(|i| roster.get(i)).collect()
+    }
+
+    //(token, salary, pay_period, last_paid, amount_due)
+    pub fn worker_info(
+        &self,
+        employer: Address,
+        worker: Address,
+    ) -> Result<(Address, U256, u64, u64, U256), EmployerPoolError> {
+        let now = self.vm().block_timestamp();
+        let registered = self.workers.getter(employer);
+        let entry = registered.getter(worker);
+        if !entry.active.get() {
+            return Err(EmployerPoolError::WorkerNotRegistered(WorkerNotRegistered { worker }));
+        }
+        Ok((
+            entry.token.get(),
+            entry.salary.get(),
+            entry.pay_period.get().to::<u64>(),
+            entry.last_paid.get().to::<u64>(),
+            entry.amount_due(now),
+        ))
+    }
+
+    //pays amounts[i] to recipients[i] every interval seconds from start (0 for now), interval 0 pays once
+    //tip is paid to whoever executes a run, 0 relies on the employer or a keeper running it for free
+    pub fn create_schedule(
+        &mut self,
+        token: Address,
+        recipients: Vec<Address>,
+        amounts: Vec<U256>,
+        interval: u64,
+        start: u64,
+        tip: U256,
+    ) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::only_allowed(self, token)?;
+        if recipients.len() != amounts.len() {
+            return Err(EmployerPoolError::PayrollLengthMismatch(PayrollLengthMismatch {
+                workers: U256::from(recipients.len()),
+                amounts: U256::from(amounts.len()),
+            }));
+        }
+        assert!(!recipients.is_empty(), "Schedule needs a recipient");
+        let employer = self.vm().msg_sender();
+        let next_run = if start == 0 { self.vm().block_timestamp() } else { start };
+        let payments: Vec<(Address, U256)> = recipients.into_iter().zip(amounts).collect();
+        Self::check_not_employer(employer, &payments);
+        // the first run has to fit in what is not committed yet, it counts as committed from now on
+        let first_run = payments
+            .iter()
+            .fold(tip, |sum, (_, amount)| sum.saturating_add(*amount));
+        let available = self.available_balance(employer, token);
+        if first_run > available {
+            return Err(EmployerPoolError::FundsCommitted(FundsCommitted { available }));
+        }
+
+        let id = U256::from(self.schedules.len());
+        let mut schedule = self.schedules.grow();
+        schedule.employer.set(employer);
+        schedule.token.set(token);
+        for (recipient, amount) in payments {
+            schedule.recipients.push(recipient);
+            schedule.amounts.push(amount);
+        }
+        schedule.interval.set(U64::from(interval));
+        schedule.next_run.set(U64::from(next_run));
+        schedule.tip.set(tip);
+        schedule.active.set(true);
+        let run_total = schedule.run_total();
+
+        self.employer_schedules.setter(employer).push(id);
+        log(self.vm(), ScheduleCreated { schedule_id: id, employer, token, run_total, interval, next_run, tip });
+        Ok(id)
+    }
+
+    pub fn cancel_schedule(&mut self, schedule_id: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        let mut schedule = self.schedule_mut(schedule_id)?;
+        assert_eq!(schedule.employer.get(), sender, "Only the schedule employer can cancel");
+        if !schedule.active.get() {
+            return Err(EmployerPoolError::ScheduleNotActive(ScheduleNotActive { schedule_id }));
+        }
+        schedule.active.set(false);
+        log(self.vm(), ScheduleCancelled { schedule_id });
+        Ok(())
+    }
+
+    //permissionless, pays one due run of the schedule and the tip to the caller
+    //all or nothing, reverts with UnpayableWorker if a recipient cannot be paid
+    //a run only spends what is not committed to workers or other schedules
+    pub fn execute_due(&mut self, employer: Address, schedule_id: U256) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
+        let keeper = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+
+        let schedule = self.schedule(schedule_id)?;
+        if schedule.employer.get() != employer {
+            return Err(EmployerPoolError::UnknownSchedule(UnknownSchedule { schedule_id }));
+        }
+        if !schedule.active.get() {
+            return Err(EmployerPoolError::ScheduleNotActive(ScheduleNotActive { schedule_id }));
+        }
+        let next_run = schedule.next_run.get().to::<u64>();
+        if !schedule.is_due(now) {
+            return Err(EmployerPoolError::ScheduleNotDue(ScheduleNotDue { schedule_id, next_run }));
+        }
+        let token = schedule.token.get();
+        let tip = schedule.tip.get();
+        let payments = schedule.payments();
+        let amount = schedule.run_total();
+        let own_commitment = schedule.committed_at(now);
+        let others = self.committed_balance(employer, token).saturating_sub(own_commitment);
+        let limit = self.employer_token_balance(employer, token).saturating_sub(others);
+
+        let mut schedule = self.schedule_mut(schedule_id)?;
+        schedule.advance();
+        let next_run = schedule.next_run.get().to::<u64>();
+
+        // the tip comes out of the same limit, so it is set aside first
+        Self::pay_all_within(self, employer, token, payments, limit.saturating_sub(tip))?;
+        if tip > U256::ZERO {
+            self.debit(employer, token, tip)?;
+            Self::transfer_token(self, token, keeper, tip)?;
+        }
+        log(self.vm(), ScheduleExecuted { schedule_id, keeper, amount, tip, next_run });
+        Ok(amount)
+    }
+
+    //(employer, token, recipients, amounts, interval, next_run, tip, active)
+    #[allow(clippy::type_complexity)]
+    pub fn schedule_info(
+        &self,
+        schedule_id: U256,
+    ) -> Result<(Address, Address, Vec<Address>, Vec<U256>, u64, u64, U256, bool), EmployerPoolError> {
+        let schedule = self.schedule(schedule_id)?;
+        let (recipients, amounts) = schedule.payments().into_iter().unzip();
+        Ok((
+            schedule.employer.get(),
+            schedule.token.get(),
+            recipients,
+            amounts,
+            schedule.interval.get().to::<u64>(),
+            schedule.next_run.get().to::<u64>(),
+            schedule.tip.get(),
+            schedule.active.get(),
+        ))
+    }
+
+    pub fn employer_schedules(&self, employer: Address) -> Vec<U256> {
+        let ids = self.employer_schedules.getter(employer);
+        (0..ids.len()).filter_map(|i| ids.get(i)).collect()
+    }
+
+    //schedules a keeper can execute right now
+    pub fn overdue_schedules(&self) -> Vec<U256> {
+        let now = self.vm().block_timestamp();
+        (0..self.schedules.len())
+            .filter(|i| self.schedules.getter(*i).is_some_and(|schedule| schedule.is_due(now)))
+            .map(U256::from)
+            .collect()
+    }
+
+    //schedules that become due within the next `within` seconds
+    pub fn upcoming_schedules(&self, within: u64) -> Vec<U256> {
+        let now = self.vm().block_timestamp();
+        let until = now.saturating_add(within);
+        (0..self.schedules.len())
+            .filter(|i| {
+                self.schedules.getter(*i).is_some_and(|schedule| {
+                    let next_run = schedule.next_run.get().to::<u64>();
+                    schedule.active.get() && next_run > now && next_run <= until
+                })
+            })
+            .map(U256::from)
+            .collect()
+    }
+
+    //lifetime totals, deposits are counted net of transfer fees
+    pub fn total_deposited(&self, employer: Address, token: Address) -> U256 {
+        self.ledger.deposited(employer, token)
+    }
+
+    //everything paid to workers through payroll, streams, grants and distributions
+    pub fn total_paid_out(&self, employer: Address, token: Address) -> U256 {
+        self.ledger.paid_out(employer, token)
+    }
+
+    pub fn paid_to_worker(&self, employer: Address, worker: Address, token: Address) -> U256 {
+        self.ledger.paid_to_worker(employer, worker, token)
+    }
+
+    pub fn payment_record_count(&self) -> U256 {
+        self.ledger.record_count()
+    }
+
+    //(run_id, employer, worker, token, amount, timestamp), oldest first, see ledger
+    pub fn payment_records(&self, offset: U256, limit: U256) -> Vec<RecordView> {
+        self.ledger.records(offset, limit)
+    }
+
+    pub fn employer_payment_count(&self, employer: Address) -> U256 {
+        self.ledger.employer_record_count(employer)
+    }
+
+    pub fn employer_payment_records(&self, employer: Address, offset: U256, limit: U256) -> Vec<RecordView> {
+        self.ledger.employer_records(employer, offset, limit)
+    }
+
+    pub fn worker_payment_count(&self, worker: Address) -> U256 {
+        self.ledger.worker_record_count(worker)
+    }
+
+    pub fn worker_payment_records(&self, worker: Address, offset: U256, limit: U256) -> Vec<RecordView> {
+        self.ledger.worker_records(worker, offset, limit)
+    }
+
+    //pull payments: payroll only credits workers, who withdraw with claim
+    pub fn set_pull_payments(&mut self, enabled: bool) {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        self.pull_payments.setter(employer).set(enabled);
+        log(self.vm(), PullPaymentsSet { employer, enabled });
+    }
+
+    pub fn uses_pull_payments(&self, employer: Address) -> bool {
+        self.pull_payments.get(employer)
+    }
+
+    pub fn claim(&mut self, token: Address) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let worker = self.vm().msg_sender();
+        Self::claim_to(self, token, worker)
+    }
+
+    pub fn claim_to(&mut self, token: Address, recipient: Address) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
+        let worker = self.vm().msg_sender();
+        let amount = self.claimable.getter(worker).get(token);
+        if amount == U256::ZERO {
+            return Ok(amount);
+        }
+
+        self.claimable.setter(worker).setter(token).set(U256::ZERO);
+        Self::transfer_token(self, token, recipient, amount)?;
+        log(self.vm(), Claimed { worker, recipient, token, amount });
+        Ok(amount)
+    }
+
+    pub fn claimable_balance(&self, worker: Address, token: Address) -> U256 {
+        self.claimable.getter(worker).get(token)
+    }
+
+    //merkle payroll for large runs, build the root and proofs with merkle::MerkleTree
+    //run_id has to be next_distribution_id(employer), the id the leaves were hashed with
+    //the full total is locked from the employer's balance until claimed or reclaimed
+    pub fn create_distribution(
+        &mut self,
+        token: Address,
+        run_id: U256,
+        root: B256,
+        total: U256,
+        expiry: u64,
+    ) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        assert!(expiry > self.vm().block_timestamp(), "Expiry must be in the future");
+        let expected = self.next_distribution_id(employer);
+        if run_id != expected {
+            return Err(EmployerPoolError::InvalidRunId(InvalidRunId { expected, run_id }));
+        }
+
+        self.debit_available(employer, token, total)?;
+
+        let nonce = self.distribution_nonces.get(employer);
+        self.distribution_nonces.setter(employer).set(nonce + U64::from(1));
+        let mut run = self.distributions.setter(run_id);
+        run.employer.set(employer);
+        run.token.set(token);
+        run.root.set(root);
+        run.total.set(total);
+        run.expiry.set(U64::from(expiry));
+        log(self.vm(), DistributionCreated { run_id, employer, token, root, total, expiry });
+        Ok(run_id)
+    }
+
+    //anyone can submit the proof, the amount always goes to the worker in the leaf
+    pub fn claim_distribution(
+        &mut self,
+        run_id: U256,
+        index: U256,
+        worker: Address,
+        amount: U256,
+        proof: Vec<B256>,
+    ) -> Result<(), EmployerPoolError> {
+        self.reentrancy.check();
+        Self::when_not_paused(self, PAYOUTS)?;
+        let now = self.vm().block_timestamp();
+        let run = self.distribution(run_id)?;
+        if run.reclaimed.get() || now > run.expiry.get().to::<u64>() {
+            return Err(EmployerPoolError::DistributionExpired(DistributionExpired { run_id }));
+        }
+        if self.is_distribution_claimed(run_id, index) {
+            return Err(EmployerPoolError::AlreadyClaimed(AlreadyClaimed { run_id, index }));
+        }
+        let leaf = merkle::leaf(run_id, index, worker, amount);
+        if !merkle::verify(&proof, run.root.get(), leaf) {
+            return Err(EmployerPoolError::InvalidProof(InvalidProof { run_id, index }));
+        }
+        let employer = run.employer.get();
+        let token = run.token.get();
+        let claimed = run.claimed.get() + amount;
+        assert!(claimed <= run.total.get(), "Distribution overdrawn");
+
+        let word = index >> 8;
+        let bit = U256::from(1) << (index & U256::from(255));
+        let bits = self.distribution_claims.getter(run_id).get(word);
+        self.distribution_claims.setter(run_id).setter(word).set(bits | bit);
+        self.distributions.setter(run_id).claimed.set(claimed);
+
+        let ledger_run = self.ledger.start_run();
+        if !self.pay_worker(ledger_run, employer, token, worker, amount) {
+            return Err(EmployerPoolError::UnpayableWorker(UnpayableWorker { worker, amount }));
+        }
+        log(self.vm(), DistributionClaimed { run_id, worker, index, amount });
+        Ok(())
+    }
+
+    //returns what nobody claimed before the expiry to the employer balance
+    pub fn reclaim_distribution(&mut self, run_id: U256) -> Result<U256, EmployerPoolError> {
+        self.reentrancy.check();
+        let now = self.vm().block_timestamp();
+        let employer = self.vm().msg_sender();
+        let run = self.distribution(run_id)?;
+        assert_eq!(run.employer.get(), employer, "Only the distribution employer can reclaim");
+        if run.reclaimed.get() {
+            return Err(EmployerPoolError::DistributionExpired(DistributionExpired { run_id }));
+        }
+        if now <= run.expiry.get().to::<u64>() {
+            return Err(EmployerPoolError::DistributionNotExpired(DistributionNotExpired { run_id }));
+        }
+        let token = run.token.get();
+        let remaining = run.total.get() - run.claimed.get();
+
+        self.distributions.setter(run_id).reclaimed.set(true);
+        self.credit(employer, token, remaining);
+        log(self.vm(), DistributionReclaimed { run_id, amount: remaining });
+        Ok(remaining)
+    }
+
+    //(employer, token, root, total, claimed, expiry, reclaimed)
+    #[allow(clippy::type_complexity)]
+    pub fn distribution_info(
+        &self,
+        run_id: U256,
+    ) -> Result<(Address, Address, B256, U256, U256, u64, bool), EmployerPoolError> {
+        let run = self.distribution(run_id)?;
+        Ok((
+            run.employer.get(),
+            run.token.get(),
+            run.root.get(),
+            run.total.get(),
+            run.claimed.get(),
+            run.expiry.get().to::<u64>(),
+            run.reclaimed.get(),
+        ))
+    }
+
+    //run id the employer's next create_distribution has to use
+    pub fn next_distribution_id(&self, employer: Address) -> U256 {
+        merkle::run_id(employer, self.distribution_nonces.get(employer).to::<u64>())
+    }
+
+    pub fn is_distribution_claimed(&self, run_id: U256, index: U256) -> bool {
+        let bits = self.distribution_claims.getter(run_id).get(index >> 8);
+        bits.bit((index & U256::from(255)).to::<usize>())
+    }
+
+    fn balance(&self) -> Result<U256, EmployerPoolError>{
+        self.token_balance_of(self.token.get(), self.vm().contract_address())
+    }
+}
+
+impl EmployerPool {
+
+    //internal func
+
+    fn only_allowed(&self, token: Address) -> Result<(), EmployerPoolError> {
+        if !self.allowed_tokens.get(token) {
+            return Err(EmployerPoolError::TokenNotAllowed(TokenNotAllowed { token }));
+        }
+        Ok(())
+    }
+
+    fn payroll_domain(&self) -> Eip712Domain {
+        eip712::domain("EmployerPool", "1", self.vm().chain_id(), self.vm().contract_address())
+    }
+
+    fn when_not_paused(&self, action: u8) -> Result<(), EmployerPoolError> {
+        self.pausable.when_not_paused(action).map_err(EmployerPoolError::ActionPaused)
+    }
+
+    fn allow_token(&mut self, token: Address) {
+        if self.allowed_tokens.get(token) {
+            return;
+        }
+        self.allowed_tokens.setter(token).set(true);
+        self.token_list.push(token);
+        log(self.vm(), TokenAllowlisted { token, allowed: true });
+    }
+
+    fn debit(&mut self, employer: Address, token: Address, amount: U256) -> Result<(), EmployerPoolError> {
+        let bal = self.employer_token_balance(employer, token);
+        if amount > bal {
+            return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance {
+                balance: bal,
+            }));
+        }
+        self.balances.setter(employer).setter(token).set(bal - amount);
+        Ok(())
+    }
+
+    //for debits that do not settle salary or a schedule run, those must not eat into committed funds
+    fn debit_available(&mut self, employer: Address, token: Address, amount: U256) -> Result<(), EmployerPoolError> {
+        let available = self.available_balance(employer, token);
+        if amount <= self.employer_token_balance(employer, token) && amount > available {
+            return Err(EmployerPoolError::FundsCommitted(FundsCommitted { available }));
+        }
+        self.debit(employer, token, amount)
+    }
+
+    fn credit(&mut self, employer: Address, token: Address, amount: U256) {
+        let bal = self.employer_token_balance(employer, token);
+        self.balances.setter(employer).setter(token).set(bal + amount);
+    }
+
+    fn schedule(&self, schedule_id: U256) -> Result<StorageGuard<Schedule>, EmployerPoolError> {
+        self.schedules
+            .getter(schedule_id)
+            .ok_or(EmployerPoolError::UnknownSchedule(UnknownSchedule { schedule_id }))
+    }
+
+    fn schedule_mut(&mut self, schedule_id: U256) -> Result<StorageGuardMut<Schedule>, EmployerPoolError> {
+        self.schedules
+            .setter(schedule_id)
+            .ok_or(EmployerPoolError::UnknownSchedule(UnknownSchedule { schedule_id }))
+    }
+
+    fn stream(&self, stream_id: U256) -> Result<StorageGuard<Stream>, EmployerPoolError> {
+        self.streams
+            .getter(stream_id)
+            .ok_or(EmployerPoolError::UnknownStream(UnknownStream { stream_id }))
+    }
+
+    fn stream_mut(&mut self, stream_id: U256) -> Result<StorageGuardMut<Stream>, EmployerPoolError> {
+        self.streams
+            .setter(stream_id)
+            .ok_or(EmployerPoolError::UnknownStream(UnknownStream { stream_id }))
+    }
+
+    fn active_stream_mut(&mut self, stream_id: U256) -> Result<StorageGuardMut<Stream>, EmployerPoolError> {
+        let stream = self.stream_mut(stream_id)?;
+        if !stream.active.get() {
+            return Err(EmployerPoolError::StreamNotActive(StreamNotActive { stream_id }));
+        }
+        Ok(stream)
+    }
+
+    fn distribution(&self, run_id: U256) -> Result<StorageGuard<Distribution>, EmployerPoolError> {
+        let run = self.distributions.getter(run_id);
+        if run.employer.get() == Address::ZERO {
+            return Err(EmployerPoolError::UnknownDistribution(UnknownDistribution { run_id }));
+        }
+        Ok(run)
+    }
+
+    fn grant(&self, grant_id: U256) -> Result<StorageGuard<Grant>, EmployerPoolError> {
+        self.grants
+            .getter(grant_id)
+            .ok_or(EmployerPoolError::UnknownGrant(UnknownGrant { grant_id }))
+    }
+
+    fn grant_mut(&mut self, grant_id: U256) -> Result<StorageGuardMut<Grant>, EmployerPoolError> {
+        self.grants
+            .setter(grant_id)
+            .ok_or(EmployerPoolError::UnknownGrant(UnknownGrant { grant_id }))
+    }
+
+    //workers the employer cannot cover or whose transfer fails are skipped and reported
+    fn pay_from(&mut self, employer: Address, token: Address, workers: Vec<(Address, U256)>, _total:U256) -> Result<Vec<u8>, EmployerPoolError> {
+        Self::when_not_paused(self, PAYOUTS)?;
+        Self::check_not_employer(employer, &workers);
+        let mut bal = Self::employer_token_balance(self, employer, token);
+        let mut available = self.available_balance(employer, token);
+
+        if _total > bal {
+            return Err(EmployerPoolError::InsufficientBalance(InsufficientBalance{
+                balance: bal,
+            }));
+        } else if _total > available {
+            return Err(EmployerPoolError::FundsCommitted(FundsCommitted { available }));
+        }
+
+        let run_id = self.ledger.start_run();
+        let mut statuses = Vec::with_capacity(workers.len());
+        for (worker_address, amount) in workers {
+            if available < amount {
+                statuses.push(PayStatus::SkippedInsufficientBalance as u8);
+                continue;
+            }
+            self.balances.setter(employer).setter(token).set(bal - amount);
+            if self.pay_worker(run_id, employer, token, worker_address, amount) {
+                bal -= amount;
+                available -= amount;
+                statuses.push(PayStatus::Paid as u8);
+            } else {
+                self.balances.setter(employer).setter(token).set(bal);
+                statuses.push(PayStatus::TransferFailed as u8);
+            }
+        }
+        let workers_paid = statuses.iter().filter(|s| **s == PayStatus::Paid as u8).count();
+        log(self.vm(), PayrollExecuted {
+            employer,
+            workers_paid: U256::from(workers_paid),
+            workers_skipped: U256::from(statuses.len() - workers_paid),
+        });
+        Ok(statuses)
+    }
+
+    //ad-hoc payments, only out of what is not committed
+    fn pay_all_from(&mut self, employer: Address, token: Address, workers: Vec<(Address, U256)>) -> Result<(), EmployerPoolError> {
+        Self::check_not_employer(employer, &workers);
+        let available = self.available_balance(employer, token);
+        Self::pay_all_within(self, employer, token, workers, available)
+    }
+
+    //pays workers in order while they fit in limit, the first one that does not reverts the run
+    fn pay_all_within(
+        &mut self,
+        employer: Address,
+        token: Address,
+        workers: Vec<(Address, U256)>,
+        mut limit: U256,
+    ) -> Result<(), EmployerPoolError> {
+        Self::when_not_paused(self, PAYOUTS)?;
+        let workers_paid = U256::from(workers.len());
+        let run_id = self.ledger.start_run();
+        for (worker, amount) in workers {
+            if amount > limit
+                || self.debit(employer, token, amount).is_err()
+                || !self.pay_worker(run_id, employer, token, worker, amount)
+            {
+                return Err(EmployerPoolError::UnpayableWorker(UnpayableWorker { worker, amount }));
+            }
+            limit -= amount;
+        }
+        log(self.vm(), PayrollExecuted { employer, workers_paid, workers_skipped: U256::ZERO });
+        Ok(())
+    }
+
+    //the employer takes funds back through request_withdrawal, never as a payee
+    fn check_not_employer(employer: Address, workers: &[(Address, U256)]) {
+        assert!(
+            workers.iter().all(|(worker, _)| *worker != employer),
+            "Use request_withdrawal to take funds back"
+        );
+    }
+
+    //the whole list has to fit in what the operator can still spend
+    fn check_operator(&self, employer: Address, operator: Address, token: Address, workers: &[(Address, U256)]) -> Result<(), EmployerPoolError> {
+        let now = self.vm().block_timestamp();
+        let operators = self.operators.getter(employer);
+        let by_token = operators.getter(operator);
+        let grant = by_token.getter(token);
+        if !grant.is_valid(now) {
+            return Err(EmployerPoolError::NotOperator(NotOperator { employer, operator }));
+        }
+
+        let requested = workers.iter().fold(U256::ZERO, |sum, (_, amount)| sum.saturating_add(*amount));
+        let remaining = grant.remaining(now);
+        if requested > remaining {
+            return Err(EmployerPoolError::OperatorCapExceeded(OperatorCapExceeded { operator, remaining }));
+        }
+        Ok(())
+    }
+
+    //counts what was actually paid against the operator's cap
+    fn record_operator_payments(&mut self, employer: Address, operator: Address, token: Address, paid: Vec<(Address, U256)>) {
+        let now = self.vm().block_timestamp();
+        let total = paid.iter().fold(U256::ZERO, |sum, (_, amount)| sum + amount);
+        let mut operators = self.operators.setter(employer);
+        let mut by_token = operators.setter(operator);
+        by_token.setter(token).record_spend(now, total);
+
+        for (worker, amount) in paid {
+            log(self.vm(), OperatorPayment { employer, operator, worker, token, amount });
+        }
+    }
+
+    //transfers to the worker, or credits their claimable balance when the employer uses pull payments
+    //recorded in the ledger under run_id when it goes through
+    fn pay_worker(&mut self, run_id: U256, employer: Address, token: Address, worker: Address, amount: U256) -> bool {
+        let paid = if self.pull_payments.get(employer) {
+            let owed = self.claimable.getter(worker).get(token);
+            self.claimable.setter(worker).setter(token).set(owed + amount);
+            true
+        } else {
+            Self::try_transfer_token(self, token, worker, amount)
+        };
+        if paid {
+            let now = self.vm().block_timestamp();
+            self.ledger.record_payment(run_id, now, employer, worker, token, amount);
+            log(self.vm(), WorkerPaid { employer, worker, token, amount });
+        }
+        paid
+    }
+
+    fn transfer_from_token(&mut self, token: Address, from: Address, to: Address, amount: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.transfer_from(token, from, to, amount)?;
+        Ok(())
+    }
+    fn transfer_token(&mut self, token: Address, to: Address, amount: U256) -> Result<(), EmployerPoolError> {
+        self.reentrancy.transfer(token, to, amount)?;
+        self.release_holdings(token, amount);
+        Ok(())
+    } //contract balance
+
+    //like transfer_token but reports a failed or reverted transfer instead of reverting
+    fn try_transfer_token(&mut self, token: Address, to: Address, amount: U256) -> bool {
+        let success = self.reentrancy.transfer(token, to, amount).is_ok();
+        if success {
+            self.release_holdings(token, amount);
+        }
+        success
+    }
+
+    fn release_holdings(&mut self, token: Address, amount: U256) {
+        let held = self.token_holdings.get(token);
+        self.token_holdings.setter(token).set(held.saturating_sub(amount));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_sol_types::{SolStruct, SolValue};
+    use stylus_sdk::testing::*;
+
+    // stands in for reentrant_token.rs, every call to the token runs `hook`
+    // against the pool first and reverts with whatever it reverted with
+    fn reentering_token(vm: &TestVM, hook: impl Fn(&mut EmployerPool) + 'static) {
+        let vm = vm.clone();
+        hostio_stubs::on_call(move |_token, _calldata| {
+            hook(&mut EmployerPool::from(&vm));
+            Ok(true.abi_encode())
+        });
+    }
+
+    #[test]
+    fn pay_workers_transfer_fails_when_the_token_reenters() {
+        let vm = TestVM::default();
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let worker = Address::repeat_byte(0x11);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        pool.set_pull_payments(false);
+        pool.token.set(token);
+        reentering_token(&vm, move |pool| {
+            let _ = pool.pay_workers(vec![(worker, U256::from(40))], U256::from(40));
+        });
+
+        let statuses = pool.pay_workers(vec![(worker, U256::from(40))], U256::from(40));
+        assert_eq!(statuses.ok(), Some(vec![PayStatus::TransferFailed as u8]));
+        assert_eq!(pool.employer_balance(employer), U256::from(100));
+    }
+
+    #[test]
+    fn pay_workers_pays_when_the_token_does_not_reenter() {
+        let vm = TestVM::default();
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let worker = Address::repeat_byte(0x11);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        pool.set_pull_payments(false);
+        pool.token.set(token);
+        reentering_token(&vm, |_| {});
+
+        let statuses = pool.pay_workers(vec![(worker, U256::from(40))], U256::from(40));
+        assert_eq!(statuses.ok(), Some(vec![PayStatus::Paid as u8]));
+        assert_eq!(pool.employer_balance(employer), U256::from(60));
+    }
+
+    // the state the pool is in while a token call it made is running
+    fn reentered(vm: &TestVM) -> EmployerPool {
+        let mut pool = EmployerPool::from(vm);
+        pool.reentrancy.lock();
+        pool
+    }
+
+    #[test]
+    #[should_panic(expected = "Reentrant call")]
+    fn pay_workers_reverts_when_reentered() {
+        let vm = TestVM::default();
+        let mut pool = reentered(&vm);
+        let worker = Address::repeat_byte(0x11);
+        let _ = pool.pay_workers(vec![(worker, U256::from(1))], U256::from(1));
+    }
+
+    #[test]
+    fn pay_workers_runs_once_the_token_call_returned() {
+        let vm = TestVM::default();
+        let mut pool = reentered(&vm);
+        pool.reentrancy.unlock();
+        assert_eq!(pool.pay_workers(vec![], U256::ZERO).ok(), Some(vec![]));
+    }
+
+    // an employer with a balance in an allowlisted token, paying into
+    // claimable balances so no token call is made
+    fn funded_pool(vm: &TestVM, employer: Address, token: Address, balance: U256) -> EmployerPool {
+        let mut pool = EmployerPool::from(vm);
+        pool.allow_token(token);
+        pool.credit(employer, token, balance);
+        vm.set_sender(employer);
+        pool.set_pull_payments(true);
+        pool
+    }
+
+    #[test]
+    fn pay_workers_atomic_names_the_first_worker_past_available() {
+        let vm = TestVM::default();
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        assert!(pool.register_worker(Address::repeat_byte(0x10), token, U256::from(40), 1_000).is_ok());
+
+        let first = Address::repeat_byte(0x11);
+        let second = Address::repeat_byte(0x12);
+        let result = pool.pay_workers_atomic(token, vec![(first, U256::from(30)), (second, U256::from(40))]);
+        assert!(matches!(
+            result,
+            Err(EmployerPoolError::UnpayableWorker(UnpayableWorker { worker, .. })) if worker == second
+        ));
+    }
+
+    #[test]
+    fn create_schedule_leaves_committed_salary_alone() {
+        let vm = TestVM::default();
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        assert!(pool.register_worker(Address::repeat_byte(0x10), token, U256::from(80), 1_000).is_ok());
+
+        let other = Address::repeat_byte(0x20);
+        let result = pool.create_schedule(token, vec![other], vec![U256::from(50)], 0, 0, U256::ZERO);
+        assert!(matches!(result, Err(EmployerPoolError::FundsCommitted(_))));
+    }
+
+    #[test]
+    fn execute_due_leaves_committed_salary_alone() {
+        let vm = TestVM::default();
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        let other = Address::repeat_byte(0x20);
+        let one_off = pool.create_schedule(token, vec![other], vec![U256::from(50)], 0, 0, U256::ZERO);
+        let Ok(schedule_id) = one_off else { panic!("schedule not created") };
+        // registered after the schedule, the salary is committed before the run
+        assert!(pool.register_worker(Address::repeat_byte(0x10), token, U256::from(80), 1_000).is_ok());
+
+        let result = pool.execute_due(employer, schedule_id);
+        assert!(matches!(result, Err(EmployerPoolError::UnpayableWorker(UnpayableWorker { worker, .. })) if worker == other));
+        assert_eq!(pool.claimable_balance(other, token), U256::ZERO);
+    }
+
+    #[test]
+    fn execute_due_spends_its_own_commitment() {
+        let vm = TestVM::default();
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        let other = Address::repeat_byte(0x20);
+        let one_off = pool.create_schedule(token, vec![other], vec![U256::from(20)], 0, 0, U256::ZERO);
+        let Ok(schedule_id) = one_off else { panic!("schedule not created") };
+        assert!(pool.register_worker(Address::repeat_byte(0x10), token, U256::from(80), 1_000).is_ok());
+
+        assert!(pool.execute_due(employer, schedule_id).is_ok());
+        assert_eq!(pool.claimable_balance(other, token), U256::from(20));
+        assert_eq!(pool.available_balance(employer, token), U256::ZERO);
+    }
+
+    #[test]
+    fn admin_delay_change_waits_out_the_current_delay() {
+        let vm = TestVM::default();
+        let mut pool = EmployerPool::from(&vm);
+        vm.set_block_timestamp(1_700_000_000);
+        vm.set_sender(Address::repeat_byte(0x01));
+        pool.initialize_admin();
+        pool.set_admin_delay(3_600);
+        assert_eq!(pool.admin_delay(), 3_600);
+
+        let now = vm.block_timestamp();
+        pool.set_admin_delay(0);
+        assert_eq!(pool.admin_delay(), 3_600);
+        assert_eq!(pool.pending_admin_delay(), (0, now + 3_600));
+
+        vm.set_block_timestamp(now + 3_600);
+        assert_eq!(pool.admin_delay(), 0);
+        assert_eq!(pool.pending_admin_delay(), (0, 0));
+    }
+
+    #[test]
+    fn guardian_set_waits_without_an_admin_delay() {
+        let vm = TestVM::default();
+        let mut pool = EmployerPool::from(&vm);
+        let admin = Address::repeat_byte(0x01);
+        vm.set_sender(admin);
+        pool.initialize_admin();
+        pool.set_guardians(vec![admin], U256::from(1));
+
+        assert!(matches!(pool.apply_guardians(), Err(EmployerPoolError::GuardianChangeNotReady(_))));
+        vm.set_block_timestamp(vm.block_timestamp() + guardian::MIN_SET_DELAY);
+        assert!(pool.apply_guardians().is_ok());
+        assert_eq!(pool.guardians(), (vec![admin], U256::from(1)));
+    }
+
+    // (employer, v, r, s) for a payroll signed by the test key made of `key` bytes
+    fn signed_payroll(pool: &EmployerPool, key: u8, token: Address, workers: &[(Address, U256)]) -> (Address, u8, B256, B256) {
+        let (employer, ..) = hostio_stubs::sign(key, B256::ZERO);
+        let payroll = Payroll {
+            employer,
+            token,
+            workers: workers.iter().map(|(worker, _)| *worker).collect(),
+            amounts: workers.iter().map(|(_, amount)| *amount).collect(),
+            nonce: pool.payroll_nonce(employer),
+            deadline: U256::MAX,
+        };
+        let (_, v, r, s) = hostio_stubs::sign(key, payroll.eip712_signing_hash(&pool.payroll_domain()));
+        (employer, v, r, s)
+    }
+
+    #[test]
+    fn pay_workers_signed_pays_what_the_employer_signed() {
+        let vm = TestVM::default();
+        let token = Address::repeat_byte(0x70);
+        let (employer, ..) = hostio_stubs::sign(0x01, B256::ZERO);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        let worker = Address::repeat_byte(0x11);
+        let (_, v, r, s) = signed_payroll(&pool, 0x01, token, &[(worker, U256::from(40))]);
+
+        vm.set_sender(Address::repeat_byte(0x99));
+        let result = pool.pay_workers_signed(employer, token, vec![worker], vec![U256::from(40)], U256::ZERO, U256::MAX, v, r, s);
+        assert!(result.is_ok());
+        assert_eq!(pool.claimable_balance(worker, token), U256::from(40));
+        assert_eq!(pool.payroll_nonce(employer), U256::from(1));
+    }
+
+    #[test]
+    fn pay_workers_signed_rejects_another_signer() {
+        let vm = TestVM::default();
+        let token = Address::repeat_byte(0x70);
+        let (employer, ..) = hostio_stubs::sign(0x01, B256::ZERO);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        let worker = Address::repeat_byte(0x11);
+        let (_, v, r, s) = signed_payroll(&pool, 0x02, token, &[(worker, U256::from(40))]);
+
+        let result = pool.pay_workers_signed(employer, token, vec![worker], vec![U256::from(40)], U256::ZERO, U256::MAX, v, r, s);
+        assert!(matches!(result, Err(EmployerPoolError::InvalidPayrollSigner(_))));
+    }
+
+    #[test]
+    fn pay_workers_signed_rejects_a_bad_signature() {
+        let vm = TestVM::default();
+        let token = Address::repeat_byte(0x70);
+        let mut pool = funded_pool(&vm, Address::ZERO, token, U256::from(100));
+        let worker = Address::repeat_byte(0x11);
+
+        let result = pool.pay_workers_signed(
+            Address::ZERO,
+            token,
+            vec![worker],
+            vec![U256::from(40)],
+            U256::ZERO,
+            U256::MAX,
+            27,
+            B256::ZERO,
+            B256::ZERO,
+        );
+        assert!(matches!(result, Err(EmployerPoolError::InvalidSignature(_))));
+        assert_eq!(pool.claimable_balance(worker, token), U256::ZERO);
+    }
+
+    #[test]
+    fn create_distribution_takes_the_next_run_id_only() {
+        let vm = TestVM::default();
+        vm.set_block_timestamp(1_700_000_000);
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        let expiry = 1_700_000_000 + 86_400;
+
+        let run_id = pool.next_distribution_id(employer);
+        assert_eq!(run_id, merkle::run_id(employer, 0));
+        let wrong = pool.create_distribution(token, run_id + U256::from(1), B256::ZERO, U256::from(10), expiry);
+        assert!(matches!(wrong, Err(EmployerPoolError::InvalidRunId(_))));
+        assert_eq!(pool.create_distribution(token, run_id, B256::ZERO, U256::from(10), expiry).ok(), Some(run_id));
+        // used once
+        let again = pool.create_distribution(token, run_id, B256::ZERO, U256::from(10), expiry);
+        assert!(matches!(again, Err(EmployerPoolError::InvalidRunId(_))));
+        assert_eq!(pool.next_distribution_id(employer), merkle::run_id(employer, 1));
+    }
+
+    #[test]
+    fn claim_distribution_pays_each_leaf_once() {
+        let vm = TestVM::default();
+        vm.set_block_timestamp(1_700_000_000);
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        let workers = vec![
+            (Address::repeat_byte(0x11), U256::from(30)),
+            (Address::repeat_byte(0x12), U256::from(20)),
+            (Address::repeat_byte(0x13), U256::from(50)),
+        ];
+        let run_id = pool.next_distribution_id(employer);
+        let tree = merkle::MerkleTree::new(run_id, &workers);
+        let created = pool.create_distribution(token, run_id, tree.root(), U256::from(100), 1_700_000_000 + 86_400);
+        assert!(created.is_ok());
+
+        let (worker, amount) = workers[1];
+        let index = U256::from(1);
+        let proof = tree.proof(1).unwrap();
+        assert!(pool.claim_distribution(run_id, index, worker, amount, proof.clone()).is_ok());
+        assert!(pool.is_distribution_claimed(run_id, index));
+        assert_eq!(pool.claimable_balance(worker, token), amount);
+
+        let again = pool.claim_distribution(run_id, index, worker, amount, proof);
+        assert!(matches!(again, Err(EmployerPoolError::AlreadyClaimed(_))));
+        assert_eq!(pool.claimable_balance(worker, token), amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "Worker has salary due, run payroll first")]
+    fn update_worker_waits_for_salary_due() {
+        let vm = TestVM::default();
+        vm.set_block_timestamp(1_700_000_000);
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let worker = Address::repeat_byte(0x11);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(100));
+        assert!(pool.register_worker(worker, token, U256::from(40), 1_000).is_ok());
+
+        vm.set_block_timestamp(1_700_001_000);
+        let _ = pool.update_worker(worker, token, U256::from(80), 1_000);
+    }
+
+    #[test]
+    fn update_worker_applies_after_payroll() {
+        let vm = TestVM::default();
+        vm.set_block_timestamp(1_700_000_000);
+        let employer = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0x70);
+        let worker = Address::repeat_byte(0x11);
+        let mut pool = funded_pool(&vm, employer, token, U256::from(200));
+        assert!(pool.register_worker(worker, token, U256::from(40), 1_000).is_ok());
+
+        vm.set_block_timestamp(1_700_001_000);
+        assert_eq!(pool.run_payroll().ok(), Some((1, 0)));
+        assert!(pool.update_worker(worker, token, U256::from(80), 1_000).is_ok());
+        vm.set_block_timestamp(1_700_002_000);
+        assert_eq!(pool.run_payroll().ok(), Some((1, 0)));
+        // the first period at the old salary, the second at the new one
+        assert_eq!(pool.claimable_balance(worker, token), U256::from(120));
     }
-    fn transfer_token(&mut self, to: Address, amount: U256) -> bool {
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-        token
-            .transfer(self, to, amount)
-            .expect("approve token first")
-    } //contract balance
 }
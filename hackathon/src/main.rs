@@ -7,4 +7,5 @@ pub extern "C" fn main() {}
 #[cfg(feature = "export-abi")]
 fn main() {
     stylus_hello_world::print_abi("MIT-OR-APACHE-2.0", "pragma solidity ^0.8.23;");
+    stylus_hello_world::print_events();
 }
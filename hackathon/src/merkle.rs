@@ -0,0 +1,159 @@
+//! Merkle-root payroll distributions for [`EmployerPool`](crate::EmployerPool).
+//!
+//! A run is identified by [`run_id`], the employer's address above a
+//! per-employer nonce, so the id can be read before the run is posted and
+//! no other account can take it.
+//!
+//! A leaf is `keccak256(abi.encodePacked(run_id, index, worker, amount))`
+//! where `index` is the worker's position in the run, used for the claimed
+//! bitmap. Parent nodes hash the two children in sorted order, which matches
+//! OpenZeppelin's `MerkleProof`, so proofs don't need to carry directions.
+//!
+//! [`MerkleTree`] builds the root and proofs off-chain from the same leaves
+//! the contract verifies.
+
+use alloc::vec::Vec;
+use stylus_sdk::{
+    alloy_primitives::{Address, B256, U256},
+    crypto::keccak,
+};
+
+/// Id of the employer's run with the given nonce, `employer << 96 | nonce`
+pub fn run_id(employer: Address, nonce: u64) -> U256 {
+    (U256::from_be_slice(employer.as_slice()) << 96) | U256::from(nonce)
+}
+
+/// Hash of a single (run_id, index, worker, amount) entry
+pub fn leaf(run_id: U256, index: U256, worker: Address, amount: U256) -> B256 {
+    let mut packed = Vec::with_capacity(116);
+    packed.extend_from_slice(&run_id.to_be_bytes::<32>());
+    packed.extend_from_slice(&index.to_be_bytes::<32>());
+    packed.extend_from_slice(worker.as_slice());
+    packed.extend_from_slice(&amount.to_be_bytes::<32>());
+    keccak(packed)
+}
+
+/// Hashes two nodes in sorted order
+pub fn hash_pair(a: B256, b: B256) -> B256 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut packed = [0u8; 64];
+    packed[..32].copy_from_slice(first.as_slice());
+    packed[32..].copy_from_slice(second.as_slice());
+    keccak(packed)
+}
+
+/// Checks that `leaf` is part of the tree with the given `root`
+pub fn verify(proof: &[B256], root: B256, leaf: B256) -> bool {
+    proof.iter().fold(leaf, |node, sibling| hash_pair(node, *sibling)) == root
+}
+
+/// Off-chain tree over a full payroll run.
+///
+/// Entry `i` of `workers` becomes the leaf with index `i`. An odd node at the
+/// end of a layer is carried up unchanged.
+pub struct MerkleTree {
+    layers: Vec<Vec<B256>>,
+}
+
+impl MerkleTree {
+    pub fn new(run_id: U256, workers: &[(Address, U256)]) -> Self {
+        let leaves = workers
+            .iter()
+            .enumerate()
+            .map(|(i, (worker, amount))| leaf(run_id, U256::from(i), *worker, *amount))
+            .collect();
+        Self::from_leaves(leaves)
+    }
+
+    pub fn from_leaves(leaves: Vec<B256>) -> Self {
+        let mut layers = alloc::vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(*a, *b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    /// Root to post with `create_distribution`, zero for an empty run
+    pub fn root(&self) -> B256 {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first().copied())
+            .unwrap_or_default()
+    }
+
+    /// Proof for the leaf at `index`, or `None` if it is out of range
+    pub fn proof(&self, index: usize) -> Option<Vec<B256>> {
+        if index >= self.layers[0].len() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        let mut index = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(size: u8) -> Vec<(Address, U256)> {
+        (1..=size)
+            .map(|i| (Address::repeat_byte(i), U256::from(i) * U256::from(100)))
+            .collect()
+    }
+
+    #[test]
+    fn every_proof_verifies_against_the_root() {
+        let run_id = run_id(Address::repeat_byte(0x01), 0);
+        // odd sizes carry a node up unchanged
+        for size in 1..=7 {
+            let workers = run(size);
+            let tree = MerkleTree::new(run_id, &workers);
+            for (i, (worker, amount)) in workers.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(verify(&proof, tree.root(), leaf(run_id, U256::from(i), *worker, *amount)));
+            }
+            assert!(tree.proof(workers.len()).is_none());
+        }
+    }
+
+    #[test]
+    fn a_proof_only_verifies_its_own_leaf() {
+        let run_id = run_id(Address::repeat_byte(0x01), 0);
+        let workers = run(5);
+        let tree = MerkleTree::new(run_id, &workers);
+        let (worker, amount) = workers[2];
+        let proof = tree.proof(2).unwrap();
+
+        assert!(!verify(&proof, tree.root(), leaf(run_id, U256::from(2), worker, amount + U256::from(1))));
+        assert!(!verify(&proof, tree.root(), leaf(run_id, U256::from(3), worker, amount)));
+        let other_run = run_id + U256::from(1);
+        assert!(!verify(&proof, tree.root(), leaf(other_run, U256::from(2), worker, amount)));
+    }
+
+    #[test]
+    fn run_ids_do_not_collide_across_employers() {
+        let first = Address::repeat_byte(0x01);
+        let second = Address::repeat_byte(0x02);
+        assert_ne!(run_id(first, 0), run_id(second, 0));
+        assert_ne!(run_id(first, 1), run_id(first, 0));
+        assert_eq!(run_id(first, 7) >> 96, U256::from_be_slice(first.as_slice()));
+    }
+}
@@ -0,0 +1,42 @@
+//! Delegated payroll operators for [`EmployerPool`](crate::EmployerPool).
+//!
+//! An employer can let another address, such as HR staff or a payroll bot,
+//! run `auto_pay_workers` from its balance in one token. The grant can
+//! expire and can cap how much the operator spends per period.
+
+use stylus_sdk::alloy_primitives::{U256, U64};
+
+use crate::Operator;
+
+impl Operator {
+    /// Granted and not expired at `now`
+    pub fn is_valid(&self, now: u64) -> bool {
+        let expiry = self.expiry.get().to::<u64>();
+        self.active.get() && (expiry == 0 || now < expiry)
+    }
+
+    /// What the operator may still spend in the current period
+    pub fn remaining(&self, now: u64) -> U256 {
+        let cap = self.cap.get();
+        if cap == U256::ZERO {
+            return U256::MAX;
+        }
+        let spent = if self.period_over(now) { U256::ZERO } else { self.spent.get() };
+        cap.saturating_sub(spent)
+    }
+
+    /// Counts `amount` against the cap, starting a new period if the last one ended
+    pub fn record_spend(&mut self, now: u64, amount: U256) {
+        if self.period_over(now) {
+            self.period_start.set(U64::from(now));
+            self.spent.set(U256::ZERO);
+        }
+        let spent = self.spent.get() + amount;
+        self.spent.set(spent);
+    }
+
+    fn period_over(&self, now: u64) -> bool {
+        let period = self.cap_period.get().to::<u64>();
+        period != 0 && now >= self.period_start.get().to::<u64>() + period
+    }
+}
@@ -0,0 +1,107 @@
+//! Circuit breaker shared by the pool, trivia and token contracts.
+//!
+//! A pauser, separate from the admin, can halt groups of actions at once
+//! and optionally set a time after which they resume on their own. Each
+//! contract decides which of its methods belong to which group; views and
+//! the guarded emergency paths are never paused.
+//!
+//! Contracts emit their own `Paused`/`Unpaused` events around these calls.
+
+// each contract only uses some of the action groups
+#![allow(dead_code)]
+
+use stylus_sdk::{
+    alloy_primitives::{Address, U64, U8},
+    alloy_sol_types::sol,
+    prelude::*,
+};
+
+/// Incoming funds, e.g. `deposit`
+pub const DEPOSITS: u8 = 1;
+/// Funds leaving a pool, e.g. payroll, claims and withdrawals
+pub const PAYOUTS: u8 = 1 << 1;
+/// Prize payouts
+pub const REWARDS: u8 = 1 << 2;
+/// Token transfers between holders
+pub const TRANSFERS: u8 = 1 << 3;
+
+sol_storage! {
+    pub struct Pausable {
+        address pauser;
+        /// Bit set of the paused action groups
+        uint8 paused;
+        /// 0 means the pause lasts until `unpause`
+        uint64 paused_until;
+    }
+}
+
+sol! {
+    error ActionPaused(uint8 action);
+}
+
+impl Pausable {
+    pub fn pauser(&self) -> Address {
+        self.pauser.get()
+    }
+
+    /// Replaces the pauser, returns the previous one. Callers check who may do this.
+    pub fn set_pauser(&mut self, pauser: Address) -> Address {
+        let previous = self.pauser.get();
+        self.pauser.set(pauser);
+        previous
+    }
+
+    /// Pauses `actions` on top of whatever is paused already. A non zero
+    /// `until` applies to every paused action.
+    pub fn pause(&mut self, actions: u8, until: u64) {
+        self.only_pauser();
+        assert!(
+            until == 0 || until > self.vm().block_timestamp(),
+            "Unpause time must be in the future"
+        );
+        let paused = self.paused_actions() | actions;
+        self.paused.set(U8::from(paused));
+        self.paused_until.set(U64::from(until));
+    }
+
+    pub fn unpause(&mut self, actions: u8) {
+        self.only_pauser();
+        let paused = self.paused_actions() & !actions;
+        self.paused.set(U8::from(paused));
+        if paused == 0 {
+            self.paused_until.set(U64::ZERO);
+        }
+    }
+
+    /// Currently paused action groups, empty once `paused_until` has passed
+    pub fn paused_actions(&self) -> u8 {
+        let until = self.paused_until.get().to::<u64>();
+        if until != 0 && self.vm().block_timestamp() >= until {
+            return 0;
+        }
+        self.paused.get().to::<u8>()
+    }
+
+    pub fn paused_until(&self) -> u64 {
+        self.paused_until.get().to::<u64>()
+    }
+
+    pub fn is_paused(&self, action: u8) -> bool {
+        self.paused_actions() & action != 0
+    }
+
+    pub fn when_not_paused(&self, action: u8) -> Result<(), ActionPaused> {
+        if self.is_paused(action) {
+            return Err(ActionPaused { action });
+        }
+        Ok(())
+    }
+
+    fn only_pauser(&self) {
+        assert_eq!(
+            self.pauser.get(),
+            self.vm().msg_sender(),
+            "Only pauser can pause or unpause"
+        );
+    }
+}
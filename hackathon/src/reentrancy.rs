@@ -0,0 +1,62 @@
+//! Reentrancy guard shared by the pool and trivia contracts.
+//!
+//! The lock is held for the duration of every external token call, and each
+//! mutating entry point checks it first, so a token that calls back into the
+//! contract mid-transfer reverts instead of seeing half-updated balances.
+//! Token calls go through [`ReentrancyGuard::transfer`] and
+//! [`ReentrancyGuard::transfer_from`], which take the lock around the
+//! `safe_erc20` call, so state has to be written before them.
+//!
+//! The SDK already rejects reentrant calls unless the `reentrant` feature is
+//! enabled. The guard keeps the contracts safe if that feature is ever turned
+//! on, and balance updates still follow checks-effects-interactions.
+
+use stylus_sdk::{alloy_primitives::{Address, U256}, prelude::*};
+
+use crate::safe_erc20::{self, TokenError};
+
+sol_storage! {
+    pub struct ReentrancyGuard {
+        bool entered;
+    }
+}
+
+impl ReentrancyGuard {
+    /// Reverts if an external call is in progress
+    pub fn check(&self) {
+        assert!(!self.entered.get(), "Reentrant call");
+    }
+
+    /// Taken right before an external call
+    pub fn lock(&mut self) {
+        self.check();
+        self.entered.set(true);
+    }
+
+    /// Released once the external call has returned
+    pub fn unlock(&mut self) {
+        self.entered.set(false);
+    }
+
+    /// [`safe_erc20::transfer`] with the lock held during the call
+    pub fn transfer(&mut self, token: Address, to: Address, amount: U256) -> Result<(), TokenError> {
+        self.lock();
+        let result = safe_erc20::transfer(self.vm(), token, to, amount);
+        self.unlock();
+        result
+    }
+
+    /// [`safe_erc20::transfer_from`] with the lock held during the call
+    pub fn transfer_from(
+        &mut self,
+        token: Address,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<(), TokenError> {
+        self.lock();
+        let result = safe_erc20::transfer_from(self.vm(), token, from, to, amount);
+        self.unlock();
+        result
+    }
+}
@@ -0,0 +1,92 @@
+// Only run this as a WASM if the export-abi feature is not set.
+#![cfg_attr(not(any(feature = "export-abi", test)), no_main)]
+extern crate alloc;
+
+// Mock token for checking the reentrancy guard. Every transfer and
+// transfer_from calls back into `target` with `payload`, e.g. an encoded
+// `pay_workers` or `reward_winners` call, and reverts with whatever the
+// target reverted with. Deploy against a pool built with the `reentrant`
+// sdk feature to hit the pool's own guard instead of the sdk's.
+
+// Modules and imports
+mod eip712;
+mod erc20;
+mod pausable;
+
+use alloc::vec::Vec;
+use alloy_primitives::{Address, U256};
+use stylus_sdk::{
+    abi::Bytes,
+    call::RawCall,
+    prelude::*
+};
+use crate::erc20::{Erc20, Erc20Params};
+
+/// Immutable definitions
+struct ReentrantTokenParams;
+impl Erc20Params for ReentrantTokenParams {
+    const NAME: &'static str = "REENTRANT MOCK";
+    const SYMBOL: &'static str = "$REMOCK";
+    const DECIMALS: u8 = 6;
+}
+
+sol_storage! {
+    #[entrypoint]
+    struct ReentrantToken {
+        #[borrow]
+        Erc20<ReentrantTokenParams> erc20;
+        /// Contract called back on every transfer, zero disables the hook
+        address target;
+        bytes payload;
+    }
+}
+
+#[public]
+#[inherit(Erc20<ReentrantTokenParams>)]
+impl ReentrantToken {
+    /// Mints tokens to another address
+    pub fn mint_to(&mut self, to: Address, value: U256) -> Result<(), Vec<u8>> {
+        self.erc20.mint(to, value)?;
+        Ok(())
+    }
+
+    /// Burns tokens
+    pub fn burn(&mut self, value: U256) -> Result<(), Vec<u8>> {
+        let sender = self.vm().msg_sender();
+        self.erc20.burn(sender, value)?;
+        Ok(())
+    }
+
+    /// Sets the call made from inside every transfer
+    pub fn set_hook(&mut self, target: Address, payload: Bytes) {
+        self.target.set(target);
+        self.payload.set_bytes(payload);
+    }
+
+    /// Transfers, then calls back into the hook target
+    pub fn transfer(&mut self, to: Address, value: U256) -> Result<bool, Vec<u8>> {
+        let sender = self.vm().msg_sender();
+        self.erc20._transfer(sender, to, value)?;
+        self.reenter()?;
+        Ok(true)
+    }
+
+    /// Transfers from `from` without checking the allowance, then calls back into the hook target
+    pub fn transfer_from(&mut self, from: Address, to: Address, value: U256) -> Result<bool, Vec<u8>> {
+        self.erc20._transfer(from, to, value)?;
+        self.reenter()?;
+        Ok(true)
+    }
+}
+
+impl ReentrantToken {
+    fn reenter(&mut self) -> Result<(), Vec<u8>> {
+        let target = self.target.get();
+        if target == Address::ZERO {
+            return Ok(());
+        }
+        let payload = self.payload.get_bytes();
+        RawCall::new().call(target, &payload)?;
+        Ok(())
+    }
+}
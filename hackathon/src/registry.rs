@@ -0,0 +1,39 @@
+//! On-chain worker registry for [`EmployerPool`](crate::EmployerPool).
+//!
+//! Each employer keeps a roster of workers with a salary and a pay period.
+//! `run_payroll` pays every whole period that has passed since `last_paid`
+//! and moves `last_paid` forward by those periods, so a late run does not
+//! shift the worker's pay schedule.
+//!
+//! Salary that is due or being earned counts as committed and cannot be
+//! withdrawn by the employer, see `execute_withdrawal`.
+
+use stylus_sdk::alloy_primitives::U256;
+
+use crate::Worker;
+
+impl Worker {
+    /// Whole pay periods elapsed since the worker was last paid
+    pub fn periods_due(&self, now: u64) -> u64 {
+        let period = self.pay_period.get().to::<u64>();
+        let last_paid = self.last_paid.get().to::<u64>();
+        if !self.active.get() || period == 0 || now < last_paid {
+            return 0;
+        }
+        (now - last_paid) / period
+    }
+
+    /// Salary owed for the periods due at `now`
+    pub fn amount_due(&self, now: u64) -> U256 {
+        self.salary.get() * U256::from(self.periods_due(now))
+    }
+
+    /// What the employer has to keep for this worker at `now`, everything
+    /// due plus the period in progress
+    pub fn committed_at(&self, now: u64) -> U256 {
+        if !self.active.get() {
+            return U256::ZERO;
+        }
+        self.amount_due(now) + self.salary.get()
+    }
+}
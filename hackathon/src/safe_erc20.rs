@@ -0,0 +1,134 @@
+//! SafeERC20-style token calls shared by the pool, trivia and counter contracts.
+//!
+//! Calls go through `RawCall` so the return data can be checked by hand:
+//! - a reverted call surfaces as `TokenCallFailed` with the revert data
+//! - empty return data is accepted for tokens that don't return a bool
+//!   (USDT style), as long as there is code at the token address
+//! - otherwise the first word has to be an ABI encoded bool, `false` surfaces
+//!   as `TokenReturnedFalse` and anything else as `InvalidTokenReturn`
+//!
+//! Contracts wrap [`TokenError`] in their own error type or return it as is.
+
+// not every contract makes every kind of call
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+use stylus_sdk::{
+    alloy_primitives::{Address, B256, U256},
+    alloy_sol_types::{sol, SolCall},
+    call::RawCall,
+    prelude::*,
+    stylus_core::Host,
+};
+
+sol! {
+    function balanceOf(address account) returns (uint256);
+    function transfer(address recipient, uint256 amount) returns (bool);
+    function transferFrom(address sender, address recipient, uint256 amount) returns (bool);
+    function allowance(address owner, address spender) returns (uint256);
+    function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s);
+
+    error TokenCallFailed(address token, bytes reason);
+    error TokenReturnedFalse(address token);
+    error InvalidTokenReturn(address token);
+}
+
+#[derive(SolidityError)]
+pub enum TokenError {
+    TokenCallFailed(TokenCallFailed),
+    TokenReturnedFalse(TokenReturnedFalse),
+    InvalidTokenReturn(InvalidTokenReturn),
+}
+
+/// Transfers `amount` of the caller contract's tokens to `to`
+pub fn transfer(vm: &dyn Host, token: Address, to: Address, amount: U256) -> Result<(), TokenError> {
+    let calldata = transferCall { recipient: to, amount }.abi_encode();
+    call_returning_bool(vm, token, &calldata)
+}
+
+/// Moves `amount` from `from` to `to`, the caller contract needs the allowance
+pub fn transfer_from(
+    vm: &dyn Host,
+    token: Address,
+    from: Address,
+    to: Address,
+    amount: U256,
+) -> Result<(), TokenError> {
+    let calldata = transferFromCall { sender: from, recipient: to, amount }.abi_encode();
+    call_returning_bool(vm, token, &calldata)
+}
+
+pub fn balance_of(token: Address, owner: Address) -> Result<U256, TokenError> {
+    call_returning_uint(token, &balanceOfCall { account: owner }.abi_encode())
+}
+
+pub fn allowance(token: Address, owner: Address, spender: Address) -> Result<U256, TokenError> {
+    call_returning_uint(token, &allowanceCall { owner, spender }.abi_encode())
+}
+
+/// Approves `spender` with `owner`'s EIP-2612 signature.
+///
+/// Anyone can submit a signed permit, so it may already have been used by the
+/// time this runs. A failed call still counts when the allowance is in place.
+#[allow(clippy::too_many_arguments)]
+pub fn permit(
+    token: Address,
+    owner: Address,
+    spender: Address,
+    value: U256,
+    deadline: U256,
+    v: u8,
+    r: B256,
+    s: B256,
+) -> Result<(), TokenError> {
+    let calldata = permitCall { owner, spender, value, deadline, v, r, s }.abi_encode();
+    match RawCall::new().call(token, &calldata) {
+        Ok(_) => Ok(()),
+        Err(reason) => {
+            if allowance(token, owner, spender)? >= value {
+                return Ok(());
+            }
+            Err(call_failed(token, reason))
+        }
+    }
+}
+
+fn call_returning_uint(token: Address, calldata: &[u8]) -> Result<U256, TokenError> {
+    let data = RawCall::new_static()
+        .call(token, calldata)
+        .map_err(|reason| call_failed(token, reason))?;
+    let word: [u8; 32] = data
+        .get(..32)
+        .and_then(|word| word.try_into().ok())
+        .ok_or(TokenError::InvalidTokenReturn(InvalidTokenReturn { token }))?;
+    Ok(U256::from_be_bytes(word))
+}
+
+fn call_returning_bool(vm: &dyn Host, token: Address, calldata: &[u8]) -> Result<(), TokenError> {
+    let data = RawCall::new()
+        .call(token, calldata)
+        .map_err(|reason| call_failed(token, reason))?;
+
+    // calls to an address without code succeed with no data too
+    if data.is_empty() {
+        if vm.code_size(token) == 0 {
+            return Err(TokenError::InvalidTokenReturn(InvalidTokenReturn { token }));
+        }
+        return Ok(());
+    }
+
+    let word = data
+        .get(..32)
+        .ok_or(TokenError::InvalidTokenReturn(InvalidTokenReturn { token }))?;
+    if word[..31].iter().any(|byte| *byte != 0) || word[31] > 1 {
+        return Err(TokenError::InvalidTokenReturn(InvalidTokenReturn { token }));
+    }
+    if word[31] == 0 {
+        return Err(TokenError::TokenReturnedFalse(TokenReturnedFalse { token }));
+    }
+    Ok(())
+}
+
+fn call_failed(token: Address, reason: Vec<u8>) -> TokenError {
+    TokenError::TokenCallFailed(TokenCallFailed { token, reason: reason.into() })
+}
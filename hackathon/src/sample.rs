@@ -1,53 +1,78 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 extern crate alloc;
 
+mod admin;
+mod safe_erc20;
 
+use crate::admin::{Admin, AdminError};
+use crate::safe_erc20::TokenError;
 use stylus_sdk::{
     alloy_primitives::{Address, U256},
-    alloy_sol_types::{sol, SolCall},
-    call::RawCall, 
+    alloy_sol_types::sol,
     prelude::*,
-    storage::StorageAddress,
 };
 
 sol_storage! {
     #[entrypoint]
     pub struct Counter {
         address token;
-        address admin;
+        Admin admin;
         mapping(address => uint256) balances;
     }
 }
 
 
 
-pub struct Worker {
-    worker_address: Address,
-    amount_paid: U256,
-}
-
 sol! {
-    function balanceOf(address account) returns (uint256);
-    function transfer(address recipient, uint256 value) returns (bool);
     event CounterCreated(address counter, address owner);
+    event AdminChanged(address indexed previous_admin, address indexed new_admin);
+    event AdminChangeProposed(address indexed current_admin, address indexed pending_admin, uint64 eta);
+    event AdminChangeCancelled(address indexed pending_admin);
+    event AdminDelayChanged(uint64 delay, uint64 eta);
 }
 
 #[public]
 impl Counter {
     //constructor
     pub fn initialize_admin(&mut self) {
-        if Address::from(*self.admin.get()) == Address::default() {
-            self.admin.set(self.vm().msg_sender());
-        }
+        let admin = self.vm().msg_sender();
+        self.admin.initialize(admin);
     }
-    //change admin
+    //propose a new admin, who accepts once the admin delay has passed
     pub fn change_admin(&mut self, new_admin: Address) {
-        assert_eq!(
-            Address::from(*self.admin.get()),
-            self.vm().msg_sender(),
-            "Only admin can change admin"
-        );
-        self.admin.set(new_admin);
+        let eta = self.admin.propose(new_admin);
+        log(self.vm(), AdminChangeProposed { current_admin: self.admin.get(), pending_admin: new_admin, eta });
+    }
+
+    pub fn accept_admin(&mut self) -> Result<(), AdminError> {
+        let previous_admin = self.admin.accept()?;
+        log(self.vm(), AdminChanged { previous_admin, new_admin: self.admin.get() });
+        Ok(())
+    }
+
+    pub fn cancel_admin_change(&mut self) {
+        let pending_admin = self.admin.cancel();
+        log(self.vm(), AdminChangeCancelled { pending_admin });
+    }
+
+    //takes effect once the current delay has passed, then applies to admin changes proposed afterwards
+    pub fn set_admin_delay(&mut self, delay: u64) {
+        let eta = self.admin.set_delay(delay);
+        log(self.vm(), AdminDelayChanged { delay, eta });
+    }
+
+    //(pending admin, time from which it can accept)
+    pub fn pending_admin(&self) -> (Address, u64) {
+        self.admin.pending()
+    }
+
+    pub fn admin_delay(&self) -> u64 {
+        self.admin.delay()
+    }
+
+    //(delay, time it takes effect), eta 0 when no change is scheduled
+    pub fn pending_admin_delay(&self) -> (u64, u64) {
+        self.admin.pending_delay()
     }
     //set token address
     pub fn set_address(&mut self, _token: Address) {
@@ -63,8 +88,7 @@ impl Counter {
         );
 
         for (worker_address, amount_paid) in workers {
-            let success = self.transfer(worker_address, amount_paid);
-            if !success {
+            if self.transfer(worker_address, amount_paid).is_err() {
                 continue;
             }
         }
@@ -74,41 +98,26 @@ impl Counter {
         Ok(Address::from(*self.admin.get()))
     }
 
-    pub fn balance_of(&self, owner: Address) -> U256 {
-        let result = RawCall::new_static().call(
-            alloy_primitives::Address(*self.token.get()),
-            &balanceOfCall { account: owner }.abi_encode(),
-        );
-
-        match result {
-            Ok(data) => U256::from_be_bytes::<32>(data.try_into().unwrap_or([0u8; 32])),
-            Err(_) => U256::from(0), // Returns 0 if the call fails
-        }
+    pub fn balance_of(&self, owner: Address) -> Result<U256, TokenError> {
+        safe_erc20::balance_of(self.token.get(), owner)
     }
-    pub fn transfer(&self, recipient: Address, amount: U256) -> bool {
+    pub fn transfer(&self, recipient: Address, amount: U256) -> Result<bool, TokenError> {
         let token_addr = alloy_primitives::Address(*self.token.get());
-        Self::perform_transfer(token_addr, recipient, amount)
+        Self::perform_transfer(self, token_addr, recipient, amount)
     }
 
-    pub fn transfer_to(&self, recipient: Address, token_add: Address, amount: U256) -> bool {
+    pub fn transfer_to(&self, recipient: Address, token_add: Address, amount: U256) -> Result<bool, TokenError> {
         let token_addr = alloy_primitives::Address(*token_add);
-        Self::perform_transfer(token_addr, recipient, amount)
+        Self::perform_transfer(self, token_addr, recipient, amount)
     }
 
     fn perform_transfer(
+        &self,
         token_addr: alloy_primitives::Address,
         recipient: Address,
         amount: U256,
-    ) -> bool {
-        let call_data = transferCall {
-            recipient,
-            value: amount,
-        }
-        .abi_encode();
-        let result = RawCall::new().call(token_addr, &call_data);
-        match result {
-            Ok(data) => data.first().copied() == Some(1),
-            Err(_) => false,
-        }
+    ) -> Result<bool, TokenError> {
+        safe_erc20::transfer(self.vm(), token_addr, recipient, amount)?;
+        Ok(true)
     }
 }
@@ -0,0 +1,72 @@
+//! Recurring payroll schedules for [`EmployerPool`](crate::EmployerPool).
+//!
+//! A schedule pays a fixed list of recipients every `interval` seconds from
+//! `next_run` on. Anyone can execute a run once it is due, and is paid the
+//! schedule's `tip` from the employer's balance for doing so. Each execution
+//! pays a single run and moves `next_run` forward by one interval, so a
+//! keeper that fell behind calls again until the schedule is no longer
+//! overdue. An interval of 0 runs once and then deactivates the schedule.
+//!
+//! The runs that are due, or the next one if none is, count as committed
+//! and cannot be withdrawn by the employer, see `execute_withdrawal`. A
+//! schedule is only created when its first run fits in what the employer has
+//! not committed yet, and a run never spends funds committed to salaries or
+//! to the employer's other schedules.
+
+use alloc::vec::Vec;
+use stylus_sdk::alloy_primitives::{Address, U256, U64};
+
+use crate::Schedule;
+
+impl Schedule {
+    /// Active and past `next_run` at `now`
+    pub fn is_due(&self, now: u64) -> bool {
+        self.active.get() && now >= self.next_run.get().to::<u64>()
+    }
+
+    /// Runs that can be executed at `now`
+    pub fn runs_due(&self, now: u64) -> u64 {
+        if !self.is_due(now) {
+            return 0;
+        }
+        let interval = self.interval.get().to::<u64>();
+        if interval == 0 {
+            return 1;
+        }
+        (now - self.next_run.get().to::<u64>()) / interval + 1
+    }
+
+    /// Recipients paired with their amount for a single run
+    pub fn payments(&self) -> Vec<(Address, U256)> {
+        (0..self.recipients.len())
+            .filter_map(|i| Some((self.recipients.get(i)?, self.amounts.get(i)?)))
+            .collect()
+    }
+
+    /// Paid to the recipients on every run, without the tip
+    pub fn run_total(&self) -> U256 {
+        (0..self.amounts.len())
+            .filter_map(|i| self.amounts.get(i))
+            .fold(U256::ZERO, |sum, amount| sum.saturating_add(amount))
+    }
+
+    /// What the employer has to keep for this schedule at `now`
+    pub fn committed_at(&self, now: u64) -> U256 {
+        if !self.active.get() {
+            return U256::ZERO;
+        }
+        let runs = self.runs_due(now).max(1);
+        self.run_total().saturating_add(self.tip.get()) * U256::from(runs)
+    }
+
+    /// Moves to the next run, or ends a one-off schedule
+    pub fn advance(&mut self) {
+        let interval = self.interval.get().to::<u64>();
+        if interval == 0 {
+            self.active.set(false);
+            return;
+        }
+        let next_run = self.next_run.get().to::<u64>() + interval;
+        self.next_run.set(U64::from(next_run));
+    }
+}
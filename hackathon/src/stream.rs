@@ -0,0 +1,39 @@
+//! Per-second salary streams for [`EmployerPool`](crate::EmployerPool).
+//!
+//! A stream escrows part of an employer's pool balance and releases it to a
+//! single worker at `rate` tokens per second. The accrued amount is
+//! checkpointed whenever the stream is paused, resumed, topped up or
+//! cancelled, so paused time never accrues.
+
+use stylus_sdk::alloy_primitives::{U256, U64};
+
+use crate::Stream;
+
+impl Stream {
+    /// Amount accrued at `now`, never more than what has been escrowed
+    pub fn accrued_at(&self, now: u64) -> U256 {
+        let stop = self.stop.get().to::<u64>();
+        let end = if stop != 0 && now > stop { stop } else { now };
+        let from = self.checkpoint_time.get().to::<u64>();
+
+        let mut accrued = self.accrued.get();
+        if !self.paused.get() && end > from {
+            accrued += self.rate.get() * U256::from(end - from);
+        }
+        accrued.min(self.deposit.get())
+    }
+
+    /// What the worker can withdraw at `now`
+    pub fn withdrawable_at(&self, now: u64) -> U256 {
+        self.accrued_at(now) - self.withdrawn.get()
+    }
+
+    /// Locks in the accrual up to `now`. Before `start` nothing changes.
+    pub fn checkpoint(&mut self, now: u64) {
+        let accrued = self.accrued_at(now);
+        self.accrued.set(accrued);
+        if now > self.checkpoint_time.get().to::<u64>() {
+            self.checkpoint_time.set(U64::from(now));
+        }
+    }
+}
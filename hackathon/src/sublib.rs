@@ -3,13 +3,14 @@
 extern crate alloc;
 
 // Modules and imports
+mod eip712;
 mod erc20;
+mod pausable;
+#[cfg(test)]
+mod hostio_stubs;
 
 use alloy_primitives::{Address, U256};
-use stylus_sdk::{
-    msg,
-    prelude::*
-};
+use stylus_sdk::prelude::*;
 use crate::erc20::{Erc20, Erc20Params, Erc20Error};
 
 /// Immutable definitions
@@ -37,7 +38,8 @@ sol_storage! {
 impl StylusToken {
     /// Mints tokens
     pub fn mint(&mut self, value: U256) -> Result<(), Erc20Error> {
-        self.erc20.mint(msg::sender(), value)?;
+        let sender = self.vm().msg_sender();
+        self.erc20.mint(sender, value)?;
         Ok(())
     }
 
@@ -49,7 +51,8 @@ impl StylusToken {
 
     /// Burns tokens
     pub fn burn(&mut self, value: U256) -> Result<(), Erc20Error> {
-        self.erc20.burn(msg::sender(), value)?;
+        let sender = self.vm().msg_sender();
+        self.erc20.burn(sender, value)?;
         Ok(())
     }
 }
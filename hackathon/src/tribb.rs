@@ -1,14 +1,23 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 extern crate alloc;
 
+mod eip712;
+mod game;
+mod pausable;
+mod reentrancy;
+mod safe_erc20;
+#[cfg(test)]
+mod hostio_stubs;
+
+use crate::game::{CANCELLED, KEY_REVEAL_GRACE, LOCKED, OPEN, RESOLVED};
+use crate::pausable::{Pausable, DEPOSITS, REWARDS};
+use crate::reentrancy::ReentrancyGuard;
+use crate::safe_erc20::TokenError;
 use stylus_sdk::{
-    alloy_primitives::{Address, U256},
-    alloy_sol_types::{sol, SolCall},
-    call,
-    call::RawCall,
-    contract,
+    abi::Bytes,
+    alloy_primitives::{Address, B256, U16, U256, U64},
+    alloy_sol_types::{sol, Eip712Domain},
     prelude::*,
-    storage::StorageAddress,
 };
 
 sol_storage! {
@@ -19,20 +28,87 @@ sol_storage! {
         address bank_admin;
         address deployer;
         mapping(address => uint256) deposits;
+        // places in basis points of what is left after the bank fee, sums to 10,000
+        uint16[] prize_bps;
+        // host => own prize table, empty uses prize_bps
+        mapping(address => uint16[]) host_prize_bps;
+        uint16 bank_fee_bps;
+        // gets the rounding remainder of a split, zero sends it to bank_admin
+        address dust_recipient;
+        Game[] games;
+        mapping(address => uint256[]) host_games;
+        // keys whose signed results resolve games, see resolve_game_signed
+        mapping(address => bool) oracles;
+        mapping(uint256 => bool) used_result_nonces;
+        Pausable pausable;
+        ReentrancyGuard reentrancy;
+    }
+
+    /// Trivia game with its own prize pool, see [`game`]
+    pub struct Game {
+        address host;
+        address token;
+        uint256 prize_pool;
+        /// Funding closes at this time
+        uint64 deadline;
+        uint8 status;
+        /// Prize table and bank fee when the game was created
+        uint16[] prize_bps;
+        uint16 bank_fee_bps;
+        /// Funder => amount, refunded if the game is cancelled, entry fees included
+        mapping(address => uint256) funded;
+        /// Paid into the prize pool by every player who joins
+        uint256 entry_fee;
+        /// 0 means no cap
+        uint256 max_players;
+        address[] players;
+        mapping(address => bool) joined;
+        /// Host's commitment to the correct answers, zero when the deployer resolves the game
+        bytes32 answer_key;
+        /// Players reveal their answers between deadline and reveal_deadline
+        uint64 reveal_deadline;
+        /// Questions in the answer key, revealed answers cannot be longer
+        uint64 key_length;
+        /// Correct answers once the host has revealed them
+        bytes key_answers;
+        mapping(address => bytes32) commitments;
+        mapping(address => bytes) answers;
+        mapping(address => bool) revealed;
     }
 }
 
-sol_interface! {
-    interface IERC20 {
-        function transferFrom(address sender, address recipient, uint256 amount) external returns (bool);
-        function balanceOf(address account) external view returns (uint256);
-        function transfer(address recipient, uint256 amount) external returns (bool);
+const BPS: u16 = 10_000;
+
+// EIP-712 message an oracle signs to resolve a game, winners in place order
+sol! {
+    struct GameResult {
+        uint256 game_id;
+        address[] winners;
+        uint256 nonce;
     }
 }
 
 sol! {
-    function balanceOf(address account) returns (uint256);
-    function transfer(address recipient, uint256 value) returns (bool);
+    event OracleSet(address indexed oracle, bool authorized);
+    event SignedResultSubmitted(uint256 indexed game_id, address indexed oracle, address indexed relayer, uint256 nonce);
+    event PrizeTableSet(address indexed host, uint16[] bps);
+    event BankFeeSet(uint16 bps);
+    event DustRecipientSet(address indexed recipient);
+    event GameCreated(uint256 indexed game_id, address indexed host, address token, uint64 deadline);
+    event GameFunded(uint256 indexed game_id, address indexed funder, uint256 amount);
+    event GameLocked(uint256 indexed game_id);
+    event GameResolved(uint256 indexed game_id, uint256 prize_pool);
+    event GameCancelled(uint256 indexed game_id);
+    event PlayerJoined(uint256 indexed game_id, address indexed player, uint256 entry_fee);
+    event AnswerKeyCommitted(uint256 indexed game_id, bytes32 commitment, uint64 key_length, uint64 reveal_deadline);
+    event AnswersCommitted(uint256 indexed game_id, address indexed player, bytes32 commitment);
+    event AnswersRevealed(uint256 indexed game_id, address indexed player, bytes answers);
+    event AnswerKeyRevealed(uint256 indexed game_id, bytes answers);
+    event PlayerPlaced(uint256 indexed game_id, address indexed player, uint256 place, uint64 score);
+    event RefundClaimed(uint256 indexed game_id, address indexed funder, uint256 amount);
+    event PauserChanged(address indexed previous_pauser, address indexed new_pauser);
+    event Paused(address indexed pauser, uint8 actions, uint64 until);
+    event Unpaused(address indexed pauser, uint8 actions);
 }
 
 #[public]
@@ -44,38 +120,61 @@ impl TriviaBase {
         bank_admin: Address,
         deployer: Address,
     ) {
+        self.reentrancy.check();
         if Address::from(*self.admin.get()) == Address::default() {
             self.token.set(_token);
             self.admin.set(admin);
             self.bank_admin.set(bank_admin);
             self.deployer.set(deployer);
+            // close to the old fixed 48/29/19 split with a 4% bank fee
+            self.bank_fee_bps.set(U16::from(400));
+            for bps in [5000u16, 3021, 1979] {
+                self.prize_bps.push(U16::from(bps));
+            }
         }
     }
 
-    pub fn deposit(&mut self, amount: U256) -> bool {
+    pub fn deposit(&mut self, amount: U256) -> Result<bool, TokenError> {
+        self.reentrancy.check();
+        assert!(!self.pausable.is_paused(DEPOSITS), "Deposits are paused");
         let sender = self.vm().msg_sender();
         let current = self.deposits.get(sender);
-        let ext_balance = Self::token_balance(self, sender);
+        let ext_balance = Self::token_balance(self, sender)?;
         if ext_balance < amount {
-            return false;
+            return Ok(false);
         }
 
-        let success = Self::transfer_from_token(self, sender, contract::address(), amount);
         self.deposits.setter(sender).set(current + amount);
-        success
+        let token = self.token.get();
+        let this = self.vm().contract_address();
+        self.reentrancy.transfer_from(token, sender, this, amount)?;
+        Ok(true)
+    }
+
+    //approves the deposit with an EIP-2612 signature instead of a separate approve
+    pub fn deposit_with_permit(&mut self, amount: U256, deadline: U256, v: u8, r: B256, s: B256) -> Result<bool, TokenError> {
+        self.reentrancy.check();
+        assert!(!self.pausable.is_paused(DEPOSITS), "Deposits are paused");
+        let token = self.token.get();
+        let sender = self.vm().msg_sender();
+        self.reentrancy.lock();
+        safe_erc20::permit(token, sender, self.vm().contract_address(), amount, deadline, v, r, s)?;
+        self.reentrancy.unlock();
+        Self::deposit(self, amount)
     }
 
     pub fn get_deposit(&self, user: Address) -> U256 {
         self.deposits.get(user)
     }
 
-    fn contract_balance(&self) -> U256 {
-        let contract_address = contract::address();
-        let contract_bal = Self::token_balance(&self, contract_address);
-        contract_bal
+    fn contract_balance(&self) -> Result<U256, TokenError> {
+        let contract_address = self.vm().contract_address();
+        let contract_bal = Self::token_balance(self, contract_address)?;
+        Ok(contract_bal)
     }
 
-    pub fn emergency_withdraw(&mut self) -> bool {
+    pub fn emergency_withdraw(&mut self) -> Result<bool, TokenError> {
+        self.reentrancy.check();
         let sender = self.vm().msg_sender();
         assert_eq!(
             Address::from(*self.admin.get()),
@@ -83,80 +182,1096 @@ impl TriviaBase {
             "Only admin can withdraw!"
         );
 
-        let contract_balance = Self::contract_balance(&self);
-        Self::transfer_token(
-            self,
+        let contract_balance = Self::contract_balance(self)?;
+        let token = self.token.get();
+        self.reentrancy.transfer(
+            token,
             alloy_primitives::Address(*self.admin.get()),
             contract_balance,
-        )
+        )?;
+        Ok(true)
     }
 
-    pub fn reward_winners(&mut self, host: Address, winners: Vec<Address>) -> bool {
+    //winners in place order, one per place of the host's prize table, see pay_prizes
+    //single allocation per host, games run side by side through create_game
+    pub fn reward_winners(&mut self, host: Address, winners: Vec<Address>) -> Result<bool, TokenError> {
+        self.reentrancy.check();
+        assert!(!self.pausable.is_paused(REWARDS), "Rewards are paused");
         let sender = self.vm().msg_sender();
         assert_eq!(
             Address::from(*self.deployer.get()),
             sender,
             "Not deployer address"
         );
-        assert_eq!(winners.len(), 3, "Must provide exactly 3 winners");
+        let table = Self::prize_table(self, host);
+        assert_eq!(winners.len(), table.len(), "Must provide one winner per place");
 
         let host_allocation = self.deposits.get(host);
         assert!(host_allocation > U256::from(0), "Host has no allocation");
 
-        let reward0 = (host_allocation * U256::from(48)) / U256::from(100);
-        let reward1 = (host_allocation * U256::from(29)) / U256::from(100);
-        let reward2 = (host_allocation * U256::from(19)) / U256::from(100);
-        let admin_reward = (host_allocation * U256::from(4)) / U256::from(100);
-
-        let contract_balance = Self::contract_balance(&self);
+        let contract_balance = Self::contract_balance(self)?;
 
         assert!(
             contract_balance >= host_allocation,
             "Insufficient contract balance"
         );
 
-        Self::transfer_token(self, winners[0], reward0);
-        Self::transfer_token(self, winners[1], reward1);
-        Self::transfer_token(self, winners[2], reward2);
+        // zero the deposit before any transfer so the allocation cannot be paid twice
+        self.deposits.setter(host).set(U256::from(0));
+
+        let token = self.token.get();
+        let bank_fee = self.bank_fee_bps.get().to::<u16>();
+        Self::pay_prizes(self, token, host_allocation, &table, bank_fee, winners)?;
+        Ok(true)
+    }
+
+    //starts a game in token (0 for the default token) that can be funded and joined until deadline
+    //players pay entry_fee into the prize pool, max_players 0 means no cap and rules out an answer key
+    //the caller's current prize table and the bank fee are fixed for the game
+    pub fn create_game(&mut self, token: Address, deadline: u64, entry_fee: U256, max_players: U256) -> U256 {
+        self.reentrancy.check();
+        let host = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        assert!(deadline > now, "Deadline must be in the future");
+        let token = if token == Address::ZERO { self.token.get() } else { token };
+        let table = Self::prize_table(self, host);
+        let bank_fee = self.bank_fee_bps.get();
+
+        let id = U256::from(self.games.len());
+        let mut game = self.games.grow();
+        game.host.set(host);
+        game.token.set(token);
+        game.deadline.set(U64::from(deadline));
+        game.set_status(OPEN);
+        for bps in table {
+            game.prize_bps.push(U16::from(bps));
+        }
+        game.bank_fee_bps.set(bank_fee);
+        game.entry_fee.set(entry_fee);
+        game.max_players.set(max_players);
+
+        self.host_games.setter(host).push(id);
+        log(self.vm(), GameCreated { game_id: id, host, token, deadline });
+        id
+    }
+
+    //adds to the game's prize pool, anyone can fund an open game before its deadline
+    pub fn fund_game(&mut self, game_id: U256, amount: U256) -> Result<(), TokenError> {
+        self.reentrancy.check();
+        assert!(!self.pausable.is_paused(DEPOSITS), "Deposits are paused");
+        let funder = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let this = self.vm().contract_address();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert!(game.is_funding(now), "Game is not open for funding");
+
+        let pool = game.prize_pool.get() + amount;
+        game.prize_pool.set(pool);
+        let funded = game.funded.get(funder) + amount;
+        game.funded.setter(funder).set(funded);
+        let token = game.token.get();
+
+        self.reentrancy.transfer_from(token, funder, this, amount)?;
+        log(self.vm(), GameFunded { game_id, funder, amount });
+        Ok(())
+    }
+
+    //pays the entry fee into the prize pool, one entry per address
+    //the host knows the answer key, so cannot play a scored game
+    pub fn join_game(&mut self, game_id: U256) -> Result<(), TokenError> {
+        self.reentrancy.check();
+        assert!(!self.pausable.is_paused(DEPOSITS), "Deposits are paused");
+        let player = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let this = self.vm().contract_address();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert!(game.is_funding(now), "Game is not open for entries");
+        assert!(!game.joined.get(player), "Already joined");
+        assert!(!game.is_full(), "Game is full");
+        assert!(!(game.is_scored() && game.host.get() == player), "The host cannot play a scored game");
 
-        Self::transfer_token(
-            self,
-            alloy_primitives::Address(*self.bank_admin.get()),
-            admin_reward,
+        let entry_fee = game.entry_fee.get();
+        game.joined.setter(player).set(true);
+        game.players.push(player);
+        let pool = game.prize_pool.get() + entry_fee;
+        game.prize_pool.set(pool);
+        let funded = game.funded.get(player) + entry_fee;
+        game.funded.setter(player).set(funded);
+        let token = game.token.get();
+
+        if entry_fee > U256::ZERO {
+            self.reentrancy.transfer_from(token, player, this, entry_fee)?;
+        }
+        log(self.vm(), PlayerJoined { game_id, player, entry_fee });
+        Ok(())
+    }
+
+    //host closes funding early, e.g. when the game starts
+    pub fn lock_game(&mut self, game_id: U256) {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert_eq!(game.host.get(), sender, "Only the host can lock the game");
+        assert_eq!(game.status(), OPEN, "Game is not open");
+        game.set_status(LOCKED);
+        log(self.vm(), GameLocked { game_id });
+    }
+
+    //pays the game's prize pool to distinct winners in place order, once it is locked or past its deadline
+    //fewer winners than places leaves the lower places unpaid
+    pub fn resolve_game(&mut self, game_id: U256, winners: Vec<Address>) -> Result<(), TokenError> {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        assert_eq!(
+            Address::from(*self.deployer.get()),
+            sender,
+            "Not deployer address"
         );
+        Self::resolve(self, game_id, winners)
+    }
 
-        self.deposits.setter(host).set(U256::from(0));
+    //resolve_game with a GameResult signed by an oracle instead of a deployer transaction, anyone can submit it
+    //each nonce resolves once, and results signed by a key that has since been removed are rejected
+    pub fn resolve_game_signed(
+        &mut self,
+        game_id: U256,
+        winners: Vec<Address>,
+        nonce: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<(), TokenError> {
+        self.reentrancy.check();
+        assert!(!self.used_result_nonces.get(nonce), "Result nonce already used");
+        let result = GameResult { game_id, winners, nonce };
+        let oracle = eip712::signer(&result, &Self::result_domain(self), v, r, s).expect("Invalid signature");
+        assert!(self.oracles.get(oracle), "Not signed by an oracle");
+        self.used_result_nonces.setter(nonce).set(true);
+
+        Self::resolve(self, game_id, result.winners)?;
+        log(self.vm(), SignedResultSubmitted { game_id, oracle, relayer: self.vm().msg_sender(), nonce });
+        Ok(())
+    }
+
+    //adds or removes an oracle key, rotate by adding the new key before removing the old one
+    pub fn set_oracle(&mut self, oracle: Address, authorized: bool) {
+        self.reentrancy.check();
+        Self::only_admin(self);
+        assert_ne!(oracle, Address::ZERO, "Oracle cannot be the zero address");
+        self.oracles.setter(oracle).set(authorized);
+        log(self.vm(), OracleSet { oracle, authorized });
+    }
+
+    //swaps old_oracle for new_oracle in one transaction
+    pub fn rotate_oracle(&mut self, old_oracle: Address, new_oracle: Address) {
+        self.reentrancy.check();
+        Self::only_admin(self);
+        assert!(self.oracles.get(old_oracle), "Not an oracle");
+        assert_ne!(new_oracle, Address::ZERO, "Oracle cannot be the zero address");
+        self.oracles.setter(old_oracle).set(false);
+        self.oracles.setter(new_oracle).set(true);
+        log(self.vm(), OracleSet { oracle: old_oracle, authorized: false });
+        log(self.vm(), OracleSet { oracle: new_oracle, authorized: true });
+    }
+
+    pub fn is_oracle(&self, oracle: Address) -> bool {
+        self.oracles.get(oracle)
+    }
+
+    pub fn result_nonce_used(&self, nonce: U256) -> bool {
+        self.used_result_nonces.get(nonce)
+    }
+
+    //EIP-712 domain oracles sign results under, name "TriviaBase" and version "1"
+    pub fn result_domain_separator(&self) -> B256 {
+        Self::result_domain(self).separator()
+    }
+
+
+    //host or admin calls the game off, funders take their money back with claim_refund
+    //anyone can once the host of a scored game is late revealing the answer key
+    pub fn cancel_game(&mut self, game_id: U256) {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let admin = self.admin.get();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        let key_overdue = game.is_scored()
+            && now >= game.reveal_deadline.get().to::<u64>().saturating_add(KEY_REVEAL_GRACE);
+        assert!(
+            game.host.get() == sender || admin == sender || key_overdue,
+            "Only the host or admin can cancel the game"
+        );
+        assert!(game.is_live(), "Game is already closed");
+        game.set_status(CANCELLED);
+        log(self.vm(), GameCancelled { game_id });
+    }
+
+    //makes the game scored on-chain, commitment is game::key_commitment of the correct answers
+    //key_length is the number of questions, the revealed key must have exactly that many answers
+    //players reveal from the deadline until reveal_deadline, the key is revealed after that
+    //only games with a player cap can be scored, the key reveal ranks every player
+    pub fn commit_answer_key(&mut self, game_id: U256, commitment: B256, key_length: u64, reveal_deadline: u64) {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert_eq!(game.host.get(), sender, "Only the host can set the answer key");
+        assert!(game.is_committing(now), "Question window is closed");
+        assert!(!game.is_scored(), "Answer key already committed");
+        assert_ne!(commitment, B256::ZERO, "Empty commitment");
+        assert!(key_length > 0, "Answer key needs a question");
+        assert!(game.max_players.get() != U256::ZERO, "Scored games need a player cap");
+        assert!(!game.joined.get(sender), "The host cannot play a scored game");
+        assert!(reveal_deadline > game.deadline.get().to::<u64>(), "Reveal deadline must be after the deadline");
+
+        game.answer_key.set(commitment);
+        game.reveal_deadline.set(U64::from(reveal_deadline));
+        game.key_length.set(U64::from(key_length));
+        log(self.vm(), AnswerKeyCommitted { game_id, commitment, key_length, reveal_deadline });
+    }
+
+    //commitment is game::answer_commitment of the player's answers, can be replaced until the deadline
+    pub fn commit_answers(&mut self, game_id: U256, commitment: B256) {
+        self.reentrancy.check();
+        let player = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert!(game.is_scored(), "Game is not scored");
+        assert!(game.is_committing(now), "Question window is closed");
+        assert!(game.joined.get(player), "Not a player");
+
+        game.commitments.setter(player).set(commitment);
+        log(self.vm(), AnswersCommitted { game_id, player, commitment });
+    }
+
+    pub fn reveal_answers(&mut self, game_id: U256, answers: Bytes, salt: B256) {
+        self.reentrancy.check();
+        let player = self.vm().msg_sender();
+        let now = self.vm().block_timestamp();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert!(game.is_scored(), "Game is not scored");
+        assert!(game.is_revealing(now), "Not in the reveal window");
+        assert!(!game.revealed.get(player), "Already revealed");
+        assert!(answers.len() as u64 <= game.key_length.get().to::<u64>(), "More answers than questions");
+        assert_eq!(
+            game.commitments.get(player),
+            game::answer_commitment(game_id, player, &answers, salt),
+            "Answers do not match the commitment"
+        );
 
-        true
+        game.answers.setter(player).set_bytes(&answers);
+        game.revealed.setter(player).set(true);
+        log(self.vm(), AnswersRevealed { game_id, player, answers: answers.0.into() });
     }
 
-    pub fn token_balance(&self, owner: Address) -> U256 {
-        let result = RawCall::new_static().call(
-            alloy_primitives::Address(*self.token.get()),
-            &balanceOfCall { account: owner }.abi_encode(),
+    //checks the key against the host's commitment, scores the revealed answers and pays the places
+    //anyone holding the key and salt can call it, a game nobody scored in is cancelled for refunds
+    pub fn reveal_answer_key(&mut self, game_id: U256, answers: Bytes, salt: B256) -> Result<(), TokenError> {
+        self.reentrancy.check();
+        assert!(!self.pausable.is_paused(REWARDS), "Rewards are paused");
+        let now = self.vm().block_timestamp();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert!(game.is_scored(), "Game is not scored");
+        assert!(game.is_live(), "Game is already closed");
+        assert!(now >= game.reveal_deadline.get().to::<u64>(), "Reveal window is still open");
+        assert_eq!(answers.len() as u64, game.key_length.get().to::<u64>(), "Key length does not match the commitment");
+        assert_eq!(
+            game.answer_key.get(),
+            game::key_commitment(game_id, &answers, salt),
+            "Answers do not match the answer key"
         );
+        game.key_answers.set_bytes(&answers);
+
+        let ranking = game.ranking(&answers);
+        if ranking.is_empty() {
+            game.set_status(CANCELLED);
+            log(self.vm(), AnswerKeyRevealed { game_id, answers: answers.0.into() });
+            log(self.vm(), GameCancelled { game_id });
+            return Ok(());
+        }
+
+        let mut table = game.prize_table();
+        table.truncate(ranking.len());
+        let winners: Vec<Address> = ranking.iter().take(table.len()).map(|(player, _)| *player).collect();
+        let prize_pool = game.prize_pool.get();
+        let token = game.token.get();
+        let bank_fee = game.bank_fee_bps.get().to::<u16>();
 
-        match result {
-            Ok(data) => U256::from_be_bytes::<32>(data.try_into().unwrap_or([0u8; 32])),
-            Err(_) => U256::from(0), // Returns 0 if the call fails
+        // the pool is spent before any transfer so it cannot be paid twice
+        game.prize_pool.set(U256::ZERO);
+        game.set_status(RESOLVED);
+
+        log(self.vm(), AnswerKeyRevealed { game_id, answers: answers.0.into() });
+        for (place, (player, score)) in ranking.into_iter().take(table.len()).enumerate() {
+            log(self.vm(), PlayerPlaced { game_id, player, place: U256::from(place), score });
         }
+        Self::pay_prizes(self, token, prize_pool, &table, bank_fee, winners)?;
+        log(self.vm(), GameResolved { game_id, prize_pool });
+        Ok(())
+    }
+
+    pub fn claim_refund(&mut self, game_id: U256) -> Result<U256, TokenError> {
+        self.reentrancy.check();
+        let funder = self.vm().msg_sender();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert_eq!(game.status(), CANCELLED, "Game is not cancelled");
+        let amount = game.funded.get(funder);
+        assert!(amount > U256::ZERO, "Nothing to refund");
+
+        game.funded.setter(funder).set(U256::ZERO);
+        let pool = game.prize_pool.get() - amount;
+        game.prize_pool.set(pool);
+        let token = game.token.get();
+
+        self.reentrancy.transfer(token, funder, amount)?;
+        log(self.vm(), RefundClaimed { game_id, funder, amount });
+        Ok(amount)
+    }
+
+    //(host, token, prize_pool, deadline, status), status is one of game::OPEN, LOCKED, RESOLVED, CANCELLED
+    pub fn game_info(&self, game_id: U256) -> (Address, Address, U256, u64, u8) {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        (
+            game.host.get(),
+            game.token.get(),
+            game.prize_pool.get(),
+            game.deadline.get().to::<u64>(),
+            game.status(),
+        )
+    }
+
+    pub fn game_prize_table(&self, game_id: U256) -> (Vec<u16>, u16) {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        (game.prize_table(), game.bank_fee_bps.get().to::<u16>())
+    }
+
+    //(entry_fee, max_players, players joined)
+    pub fn game_entry(&self, game_id: U256) -> (U256, U256, U256) {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        (game.entry_fee.get(), game.max_players.get(), U256::from(game.players.len()))
+    }
+
+    pub fn game_players(&self, game_id: U256) -> Vec<Address> {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        (0..game.players.len()).filter_map(|i| game.players.get(i)).collect()
+    }
+
+    pub fn has_joined(&self, game_id: U256, player: Address) -> bool {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        game.joined.get(player)
+    }
+
+    pub fn game_funding(&self, game_id: U256, funder: Address) -> U256 {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        game.funded.get(funder)
+    }
+
+    //(answer key commitment, key_length, reveal_deadline, revealed key), the key is empty until revealed
+    pub fn game_answer_key(&self, game_id: U256) -> (B256, u64, u64, Bytes) {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        (
+            game.answer_key.get(),
+            game.key_length.get().to::<u64>(),
+            game.reveal_deadline.get().to::<u64>(),
+            game.key_answers.get_bytes().into(),
+        )
+    }
+
+    //(commitment, revealed, answers, score against the revealed key)
+    pub fn player_answers(&self, game_id: U256, player: Address) -> (B256, bool, Bytes, u64) {
+        let game = self.games.getter(game_id).expect("Unknown game");
+        let answers = game.answers.getter(player).get_bytes();
+        let score = game::score(&answers, &game.key_answers.get_bytes());
+        (game.commitments.get(player), game.revealed.get(player), answers.into(), score)
+    }
+
+    //same hashes the contract checks, for building commitments off-chain
+    pub fn answer_commitment(game_id: U256, player: Address, answers: Bytes, salt: B256) -> B256 {
+        game::answer_commitment(game_id, player, &answers, salt)
+    }
+
+    pub fn key_commitment(game_id: U256, answers: Bytes, salt: B256) -> B256 {
+        game::key_commitment(game_id, &answers, salt)
+    }
+
+    pub fn game_count(&self) -> U256 {
+        U256::from(self.games.len())
+    }
+
+    pub fn host_games(&self, host: Address) -> Vec<U256> {
+        let ids = self.host_games.getter(host);
+        (0..ids.len()).filter_map(|i| ids.get(i)).collect()
+    }
+
+    //default prize table, basis points per place that sum to 10,000
+    pub fn set_prize_table(&mut self, bps: Vec<u16>) {
+        self.reentrancy.check();
+        Self::only_admin(self);
+        Self::validate_prize_table(&bps);
+        while self.prize_bps.pop().is_some() {}
+        for place in bps.iter() {
+            self.prize_bps.push(U16::from(*place));
+        }
+        log(self.vm(), PrizeTableSet { host: Address::ZERO, bps });
+    }
+
+    //the caller's own prize table for their games, an empty table goes back to the default
+    pub fn set_host_prize_table(&mut self, bps: Vec<u16>) {
+        self.reentrancy.check();
+        if !bps.is_empty() {
+            Self::validate_prize_table(&bps);
+        }
+        let host = self.vm().msg_sender();
+        let mut table = self.host_prize_bps.setter(host);
+        while table.pop().is_some() {}
+        for place in bps.iter() {
+            table.push(U16::from(*place));
+        }
+        log(self.vm(), PrizeTableSet { host, bps });
+    }
+
+    //prize table reward_winners uses for host
+    pub fn prize_table(&self, host: Address) -> Vec<u16> {
+        let own = self.host_prize_bps.getter(host);
+        let table = if own.len() > 0 { &*own } else { &self.prize_bps };
+        (0..table.len())
+            .filter_map(|i| table.get(i))
+            .map(|bps| bps.to::<u16>())
+            .collect()
+    }
+
+    //bank fee in basis points of the host allocation, taken before the places split the rest
+    pub fn set_bank_fee(&mut self, bps: u16) {
+        self.reentrancy.check();
+        Self::only_admin(self);
+        assert!(bps <= BPS, "Bank fee above 10,000 bps");
+        self.bank_fee_bps.set(U16::from(bps));
+        log(self.vm(), BankFeeSet { bps });
+    }
+
+    pub fn bank_fee(&self) -> u16 {
+        self.bank_fee_bps.get().to::<u16>()
+    }
+
+    pub fn set_dust_recipient(&mut self, recipient: Address) {
+        self.reentrancy.check();
+        Self::only_admin(self);
+        self.dust_recipient.set(recipient);
+        log(self.vm(), DustRecipientSet { recipient });
+    }
+
+    //where rounding remainders go, bank_admin unless set
+    pub fn dust_recipient(&self) -> Address {
+        let recipient = self.dust_recipient.get();
+        if recipient == Address::ZERO {
+            return self.bank_admin.get();
+        }
+        recipient
+    }
+
+    //circuit breaker, emergency_withdraw stays available while paused
+    pub fn set_pauser(&mut self, pauser: Address) {
+        self.reentrancy.check();
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can set the pauser"
+        );
+        let previous_pauser = self.pausable.set_pauser(pauser);
+        log(self.vm(), PauserChanged { previous_pauser, new_pauser: pauser });
+    }
+
+    //actions is a bit set of pausable::DEPOSITS and pausable::REWARDS, until 0 pauses until unpause
+    pub fn pause(&mut self, actions: u8, until: u64) {
+        self.reentrancy.check();
+        self.pausable.pause(actions, until);
+        log(self.vm(), Paused { pauser: self.vm().msg_sender(), actions, until });
+    }
+
+    pub fn unpause(&mut self, actions: u8) {
+        self.reentrancy.check();
+        self.pausable.unpause(actions);
+        log(self.vm(), Unpaused { pauser: self.vm().msg_sender(), actions });
+    }
+
+    pub fn pauser(&self) -> Address {
+        self.pausable.pauser()
+    }
+
+    //(paused actions, time they resume on their own or 0)
+    pub fn paused(&self) -> (u8, u64) {
+        (self.pausable.paused_actions(), self.pausable.paused_until())
+    }
+
+    //reverts when the token call fails instead of reporting 0
+    pub fn token_balance(&self, owner: Address) -> Result<U256, TokenError> {
+        safe_erc20::balance_of(self.token.get(), owner)
     }
 }
 
 impl TriviaBase {
     //internal func
 
-    fn transfer_from_token(&mut self, from: Address, to: Address, amount: U256) -> bool {
-        let token: IERC20 = IERC20::new(alloy_primitives::Address(*self.token.get()));
+    //shared by resolve_game and resolve_game_signed, the caller has checked who decided the winners
+    fn resolve(&mut self, game_id: U256, winners: Vec<Address>) -> Result<(), TokenError> {
+        assert!(!self.pausable.is_paused(REWARDS), "Rewards are paused");
+        let now = self.vm().block_timestamp();
+        let mut game = self.games.setter(game_id).expect("Unknown game");
+        assert!(!game.is_scored(), "Scored games resolve through reveal_answer_key");
+        assert!(game.is_live() && !game.is_funding(now), "Game cannot be resolved");
+        let mut table = game.prize_table();
+        assert!(
+            !winners.is_empty() && winners.len() <= table.len(),
+            "Must provide one winner for each place paid"
+        );
+        assert!(
+            winners.iter().enumerate().all(|(place, winner)| !winners[..place].contains(winner)),
+            "Winner listed more than once"
+        );
+        // fewer winners than places splits the pool over the places filled, as in reveal_answer_key
+        table.truncate(winners.len());
+        if !game.players.is_empty() {
+            assert!(winners.iter().all(|winner| game.joined.get(*winner)), "Winner did not join the game");
+        }
+        let prize_pool = game.prize_pool.get();
+        assert!(prize_pool > U256::ZERO, "Game has no prize pool");
+        let token = game.token.get();
+        let bank_fee = game.bank_fee_bps.get().to::<u16>();
+
+        // the pool is spent before any transfer so it cannot be paid twice
+        game.prize_pool.set(U256::ZERO);
+        game.set_status(RESOLVED);
+
+        Self::pay_prizes(self, token, prize_pool, &table, bank_fee, winners)?;
+        log(self.vm(), GameResolved { game_id, prize_pool });
+        Ok(())
+    }
+
+    fn result_domain(&self) -> Eip712Domain {
+        eip712::domain("TriviaBase", "1", self.vm().chain_id(), self.vm().contract_address())
+    }
+
+    fn only_admin(&self) {
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can mutate this"
+        );
+    }
+
+    fn validate_prize_table(bps: &[u16]) {
+        assert!(!bps.is_empty(), "Prize table needs a place");
+        let total = bps.iter().fold(0u32, |sum, place| sum + u32::from(*place));
+        assert_eq!(total, u32::from(BPS), "Prize table must sum to 10,000 bps");
+    }
+
+    fn bps_of(amount: U256, bps: u16) -> U256 {
+        amount * U256::from(bps) / U256::from(BPS)
+    }
+
+    //the bank fee comes off the top, the places split the rest and the rounding remainder goes to dust_recipient
+    fn pay_prizes(
+        &mut self,
+        token: Address,
+        amount: U256,
+        table: &[u16],
+        bank_fee: u16,
+        winners: Vec<Address>,
+    ) -> Result<(), TokenError> {
+        let admin_reward = Self::bps_of(amount, bank_fee);
+        let prize_pool = amount - admin_reward;
+        // a full table sums to 10,000, a truncated one splits the pool over the places it has
+        let shares = table.iter().fold(U256::ZERO, |sum, bps| sum + U256::from(*bps));
+        let rewards: Vec<U256> = table
+            .iter()
+            .map(|bps| prize_pool * U256::from(*bps) / shares)
+            .collect();
+        let remainder = prize_pool - rewards.iter().fold(U256::ZERO, |sum, reward| sum + reward);
+
+        for (winner, reward) in winners.into_iter().zip(rewards) {
+            self.reentrancy.transfer(token, winner, reward)?;
+        }
+
+        let bank_admin = self.bank_admin.get();
+        self.reentrancy.transfer(token, bank_admin, admin_reward)?;
+
+        if remainder > U256::ZERO {
+            let dust_recipient = Self::dust_recipient(self);
+            self.reentrancy.transfer(token, dust_recipient, remainder)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safe_erc20::{balanceOfCall, transferCall, transferFromCall};
+    use std::{cell::RefCell, rc::Rc};
+    use stylus_sdk::{
+        alloy_sol_types::{SolCall, SolStruct, SolValue},
+        testing::*,
+    };
+
+    type Transfers = Rc<RefCell<Vec<(Address, U256)>>>;
+
+    // answers balanceOf with `balance`, records every transfer and runs `hook`
+    // on the game from inside it the way reentrant_token.rs would
+    fn stand_in_token(vm: &TestVM, balance: U256, hook: impl Fn(&mut TriviaBase) + 'static) -> Transfers {
+        let transfers = Transfers::default();
+        let (vm, recorded) = (vm.clone(), transfers.clone());
+        hostio_stubs::on_call(move |_, calldata| {
+            if calldata.starts_with(&balanceOfCall::SELECTOR) {
+                return Ok(balance.abi_encode());
+            }
+            if let Ok(call) = transferCall::abi_decode(calldata, true) {
+                recorded.borrow_mut().push((call.recipient, call.amount));
+                hook(&mut TriviaBase::from(&vm));
+            }
+            assert!(
+                calldata.starts_with(&transferCall::SELECTOR) || calldata.starts_with(&transferFromCall::SELECTOR),
+                "Unexpected token call"
+            );
+            Ok(true.abi_encode())
+        });
+        transfers
+    }
+
+    #[test]
+    fn reward_winners_reverts_when_reentered() {
+        let vm = TestVM::default();
+        let mut trivia = TriviaBase::from(&vm);
+        let deployer = Address::repeat_byte(0x01);
+        let host = Address::repeat_byte(0x02);
+        trivia.initialize(Address::repeat_byte(0x03), deployer, deployer, deployer);
+        trivia.deposits.setter(host).set(U256::from(100));
+        vm.set_sender(deployer);
+
+        let winners = vec![Address::repeat_byte(0x11), Address::repeat_byte(0x12), Address::repeat_byte(0x13)];
+        let again = winners.clone();
+        let transfers = stand_in_token(&vm, U256::from(1_000), move |trivia| {
+            let _ = trivia.reward_winners(host, again.clone());
+        });
+
+        let Err(TokenError::TokenCallFailed(failed)) = trivia.reward_winners(host, winners) else {
+            panic!("the reentered call should fail the transfer");
+        };
+        assert!(String::from_utf8_lossy(&failed.reason).contains("Reentrant call"));
+        // the first prize transfer is the one that called back
+        assert_eq!(transfers.borrow().len(), 1);
+    }
+
+    #[test]
+    fn reward_winners_pays_when_the_token_does_not_reenter() {
+        let vm = TestVM::default();
+        let mut trivia = TriviaBase::from(&vm);
+        let deployer = Address::repeat_byte(0x01);
+        let host = Address::repeat_byte(0x02);
+        trivia.initialize(Address::repeat_byte(0x03), deployer, deployer, deployer);
+        trivia.deposits.setter(host).set(U256::from(100));
+        vm.set_sender(deployer);
+
+        let transfers = stand_in_token(&vm, U256::from(1_000), |_| {});
+        let winners = vec![Address::repeat_byte(0x11), Address::repeat_byte(0x12), Address::repeat_byte(0x13)];
+        assert!(trivia.reward_winners(host, winners).is_ok());
+        assert_eq!(trivia.deposits.get(host), U256::ZERO);
+        let paid = transfers.borrow().iter().fold(U256::ZERO, |sum, (_, amount)| sum + amount);
+        assert_eq!(paid, U256::from(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Winner listed more than once")]
+    fn resolve_game_rejects_duplicate_winners() {
+        let vm = TestVM::default();
+        let mut trivia = TriviaBase::from(&vm);
+        let deployer = Address::repeat_byte(0x01);
+        let host = Address::repeat_byte(0x02);
+        trivia.initialize(Address::repeat_byte(0x03), deployer, deployer, deployer);
+        vm.set_sender(host);
+        let game_id = trivia.create_game(Address::ZERO, vm.block_timestamp() + 60, U256::ZERO, U256::ZERO);
+        trivia.lock_game(game_id);
+
+        vm.set_sender(deployer);
+        let winner = Address::repeat_byte(0x11);
+        let _ = trivia.resolve_game(game_id, vec![winner, winner]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Scored games need a player cap")]
+    fn commit_answer_key_rejects_uncapped_games() {
+        let vm = TestVM::default();
+        let mut trivia = TriviaBase::from(&vm);
+        let host = Address::repeat_byte(0x02);
+        vm.set_sender(host);
+        let deadline = vm.block_timestamp() + 60;
+        let game_id = trivia.create_game(Address::repeat_byte(0x03), deadline, U256::ZERO, U256::ZERO);
+
+        trivia.commit_answer_key(game_id, B256::repeat_byte(0xaa), 10, deadline + 60);
+    }
+
+    #[test]
+    #[should_panic(expected = "The host cannot play a scored game")]
+    fn join_game_rejects_the_host_of_a_scored_game() {
+        let vm = TestVM::default();
+        let mut trivia = TriviaBase::from(&vm);
+        let host = Address::repeat_byte(0x02);
+        vm.set_sender(host);
+        let deadline = vm.block_timestamp() + 60;
+        let game_id = trivia.create_game(Address::repeat_byte(0x03), deadline, U256::ZERO, U256::from(4));
+        trivia.commit_answer_key(game_id, B256::repeat_byte(0xaa), 10, deadline + 60);
+
+        let _ = trivia.join_game(game_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "The host cannot play a scored game")]
+    fn commit_answer_key_rejects_a_host_who_joined() {
+        let vm = TestVM::default();
+        let mut trivia = TriviaBase::from(&vm);
+        let host = Address::repeat_byte(0x02);
+        vm.set_sender(host);
+        let deadline = vm.block_timestamp() + 60;
+        let game_id = trivia.create_game(Address::repeat_byte(0x03), deadline, U256::ZERO, U256::from(4));
+        assert!(trivia.join_game(game_id).is_ok());
+
+        trivia.commit_answer_key(game_id, B256::repeat_byte(0xaa), 10, deadline + 60);
+    }
+
+    const START: u64 = 1_700_000_000;
+    const ADMIN: Address = Address::repeat_byte(0x0a);
+    const BANK: Address = Address::repeat_byte(0x0b);
+    const DEPLOYER: Address = Address::repeat_byte(0x0c);
+    const TOKEN: Address = Address::repeat_byte(0x03);
+    const HOST: Address = Address::repeat_byte(0x02);
+
+    fn started(vm: &TestVM) -> TriviaBase {
+        vm.set_block_timestamp(START);
+        let mut trivia = TriviaBase::from(vm);
+        trivia.initialize(TOKEN, ADMIN, BANK, DEPLOYER);
+        trivia
+    }
 
-        token
-            .transfer_from(self, from, to, amount)
-            .expect("approve token first")
+    // a locked game in the default token with `pool` funded by the host
+    fn funded_game(vm: &TestVM, trivia: &mut TriviaBase, pool: u64) -> U256 {
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::ZERO, U256::ZERO);
+        assert!(trivia.fund_game(game_id, U256::from(pool)).is_ok());
+        trivia.lock_game(game_id);
+        game_id
     }
-    fn transfer_token(&mut self, to: Address, amount: U256) -> bool {
-        let token: IERC20 = IERC20::new(alloy_primitives::Address(*self.token.get()));
-        token
-            .transfer(self, to, amount)
-            .expect("approve token first")
+
+    fn players(count: u8) -> Vec<Address> {
+        (0..count).map(|i| Address::repeat_byte(0x11 + i)).collect()
+    }
+
+    #[test]
+    #[should_panic(expected = "Prize table must sum to 10,000 bps")]
+    fn set_prize_table_rejects_a_short_table() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        vm.set_sender(ADMIN);
+        trivia.set_prize_table(vec![5000, 4000]);
+    }
+
+    #[test]
+    fn resolve_game_splits_by_the_prize_table() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        vm.set_sender(ADMIN);
+        trivia.set_prize_table(vec![6000, 3000, 1000]);
+        let transfers = stand_in_token(&vm, U256::ZERO, |_| {});
+        let game_id = funded_game(&vm, &mut trivia, 1_000);
+
+        vm.set_sender(DEPLOYER);
+        let winners = players(3);
+        assert!(trivia.resolve_game(game_id, winners.clone()).is_ok());
+        // 4% bank fee off the top, the places split the other 960
+        let expected = vec![
+            (winners[0], U256::from(576)),
+            (winners[1], U256::from(288)),
+            (winners[2], U256::from(96)),
+            (BANK, U256::from(40)),
+        ];
+        assert_eq!(*transfers.borrow(), expected);
+        assert_eq!(trivia.game_info(game_id).4, RESOLVED);
+    }
+
+    #[test]
+    fn resolve_game_sends_the_rounding_remainder_to_the_dust_recipient() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        vm.set_sender(ADMIN);
+        trivia.set_prize_table(vec![3333, 3333, 3334]);
+        trivia.set_bank_fee(0);
+        assert_eq!(trivia.dust_recipient(), BANK);
+        let dust = Address::repeat_byte(0x0d);
+        trivia.set_dust_recipient(dust);
+        let transfers = stand_in_token(&vm, U256::ZERO, |_| {});
+        let game_id = funded_game(&vm, &mut trivia, 1_001);
+
+        vm.set_sender(DEPLOYER);
+        assert!(trivia.resolve_game(game_id, players(3)).is_ok());
+        let transfers = transfers.borrow();
+        assert_eq!(transfers.last(), Some(&(dust, U256::from(2))));
+        let paid = transfers.iter().fold(U256::ZERO, |sum, (_, amount)| sum + amount);
+        assert_eq!(paid, U256::from(1_001));
+    }
+
+    #[test]
+    fn cancel_game_refunds_each_funder_their_own_amount() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        let transfers = stand_in_token(&vm, U256::ZERO, |_| {});
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::ZERO, U256::ZERO);
+        assert!(trivia.fund_game(game_id, U256::from(300)).is_ok());
+        let funder = Address::repeat_byte(0x21);
+        vm.set_sender(funder);
+        assert!(trivia.fund_game(game_id, U256::from(200)).is_ok());
+
+        vm.set_sender(HOST);
+        trivia.cancel_game(game_id);
+        assert_eq!(trivia.claim_refund(game_id).ok(), Some(U256::from(300)));
+        vm.set_sender(funder);
+        assert_eq!(trivia.claim_refund(game_id).ok(), Some(U256::from(200)));
+
+        assert_eq!(*transfers.borrow(), vec![(HOST, U256::from(300)), (funder, U256::from(200))]);
+        assert_eq!(trivia.game_info(game_id).2, U256::ZERO);
+        assert_eq!(trivia.game_funding(game_id, funder), U256::ZERO);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the host or admin can cancel the game")]
+    fn cancel_game_is_limited_to_the_host_and_admin() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::ZERO, U256::ZERO);
+
+        vm.set_sender(Address::repeat_byte(0x21));
+        trivia.cancel_game(game_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing to refund")]
+    fn claim_refund_pays_once() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        stand_in_token(&vm, U256::ZERO, |_| {});
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::ZERO, U256::ZERO);
+        assert!(trivia.fund_game(game_id, U256::from(300)).is_ok());
+        trivia.cancel_game(game_id);
+
+        assert!(trivia.claim_refund(game_id).is_ok());
+        let _ = trivia.claim_refund(game_id);
+    }
+
+    #[test]
+    fn join_game_adds_the_entry_fee_to_the_host_deposit() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        stand_in_token(&vm, U256::ZERO, |_| {});
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::from(10), U256::from(2));
+        assert!(trivia.fund_game(game_id, U256::from(100)).is_ok());
+
+        for player in players(2) {
+            vm.set_sender(player);
+            assert!(trivia.join_game(game_id).is_ok());
+            assert_eq!(trivia.game_funding(game_id, player), U256::from(10));
+        }
+        assert_eq!(trivia.game_info(game_id).2, U256::from(120));
+        assert_eq!(trivia.game_entry(game_id), (U256::from(10), U256::from(2), U256::from(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Game is full")]
+    fn join_game_stops_at_the_player_cap() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        stand_in_token(&vm, U256::ZERO, |_| {});
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::from(10), U256::from(2));
+
+        for player in players(3) {
+            vm.set_sender(player);
+            let _ = trivia.join_game(game_id);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Already joined")]
+    fn join_game_takes_one_entry_per_address() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        stand_in_token(&vm, U256::ZERO, |_| {});
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::from(10), U256::ZERO);
+
+        vm.set_sender(players(1)[0]);
+        assert!(trivia.join_game(game_id).is_ok());
+        let _ = trivia.join_game(game_id);
+    }
+
+    #[test]
+    fn reveal_answer_key_pays_the_best_scores() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        let transfers = stand_in_token(&vm, U256::ZERO, |_| {});
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::from(100), U256::from(3));
+        let key = Bytes::from(b"abcd".to_vec());
+        let key_salt = B256::repeat_byte(0x99);
+        trivia.commit_answer_key(game_id, game::key_commitment(game_id, &key, key_salt), 4, START + 120);
+
+        // the last player scores nothing and never places
+        let players = players(3);
+        let answers: Vec<Bytes> = [&b"abxx"[..], b"abcd", b"zzzz"].iter().map(|a| Bytes::from(a.to_vec())).collect();
+        let salt = B256::repeat_byte(0x42);
+        for (player, answers) in players.iter().zip(&answers) {
+            vm.set_sender(*player);
+            assert!(trivia.join_game(game_id).is_ok());
+            trivia.commit_answers(game_id, game::answer_commitment(game_id, *player, answers, salt));
+        }
+        vm.set_block_timestamp(START + 60);
+        for (player, answers) in players.iter().zip(&answers) {
+            vm.set_sender(*player);
+            trivia.reveal_answers(game_id, answers.clone(), salt);
+        }
+
+        vm.set_block_timestamp(START + 120);
+        assert!(trivia.reveal_answer_key(game_id, key, key_salt).is_ok());
+        assert_eq!(trivia.player_answers(game_id, players[0]).3, 2);
+        assert_eq!(trivia.player_answers(game_id, players[1]).3, 4);
+        // 12 bank fee, two places split 288 in proportion to 5000 and 3021 bps, 1 dust
+        let expected = vec![
+            (players[1], U256::from(179)),
+            (players[0], U256::from(108)),
+            (BANK, U256::from(12)),
+            (BANK, U256::from(1)),
+        ];
+        assert_eq!(*transfers.borrow(), expected);
+        assert_eq!(trivia.game_info(game_id).4, RESOLVED);
+    }
+
+    #[test]
+    #[should_panic(expected = "Answers do not match the commitment")]
+    fn reveal_answers_must_match_the_commitment() {
+        let vm = TestVM::default();
+        let mut trivia = started(&vm);
+        stand_in_token(&vm, U256::ZERO, |_| {});
+        vm.set_sender(HOST);
+        let game_id = trivia.create_game(Address::ZERO, START + 60, U256::ZERO, U256::from(3));
+        trivia.commit_answer_key(game_id, B256::repeat_byte(0xaa), 4, START + 120);
+        let player = players(1)[0];
+        vm.set_sender(player);
+        assert!(trivia.join_game(game_id).is_ok());
+        let salt = B256::repeat_byte(0x42);
+        trivia.commit_answers(game_id, game::answer_commitment(game_id, player, b"abcd", salt));
+
+        vm.set_block_timestamp(START + 60);
+        trivia.reveal_answers(game_id, Bytes::from(b"abcc".to_vec()), salt);
+    }
+
+    const ORACLE_KEY: u8 = 0x41;
+
+    // game_id resolved to `winners` under `nonce`, signed with the key made of `key` bytes
+    fn signed_result(
+        trivia: &TriviaBase,
+        key: u8,
+        game_id: U256,
+        winners: &[Address],
+        nonce: u64,
+    ) -> (Address, u8, B256, B256) {
+        let result = GameResult { game_id, winners: winners.to_vec(), nonce: U256::from(nonce) };
+        hostio_stubs::sign(key, result.eip712_signing_hash(&trivia.result_domain()))
+    }
+
+    // two locked games of 100 each and a registered oracle
+    fn oracle_games(vm: &TestVM) -> (TriviaBase, Transfers) {
+        let mut trivia = started(vm);
+        let transfers = stand_in_token(vm, U256::ZERO, |_| {});
+        funded_game(vm, &mut trivia, 100);
+        funded_game(vm, &mut trivia, 100);
+        let (oracle, ..) = hostio_stubs::sign(ORACLE_KEY, B256::ZERO);
+        vm.set_sender(ADMIN);
+        trivia.set_oracle(oracle, true);
+        // anyone relays the result
+        vm.set_sender(Address::repeat_byte(0x31));
+        (trivia, transfers)
+    }
+
+    #[test]
+    fn resolve_game_signed_pays_an_oracle_result() {
+        let vm = TestVM::default();
+        let (mut trivia, transfers) = oracle_games(&vm);
+        let winners = players(1);
+        let (_, v, r, s) = signed_result(&trivia, ORACLE_KEY, U256::ZERO, &winners, 7);
+
+        assert!(trivia.resolve_game_signed(U256::ZERO, winners.clone(), U256::from(7), v, r, s).is_ok());
+        assert!(trivia.result_nonce_used(U256::from(7)));
+        assert_eq!(trivia.game_info(U256::ZERO).4, RESOLVED);
+        assert_eq!(transfers.borrow()[0], (winners[0], U256::from(96)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not signed by an oracle")]
+    fn resolve_game_signed_rejects_another_signer() {
+        let vm = TestVM::default();
+        let (mut trivia, _) = oracle_games(&vm);
+        let winners = players(1);
+        let (_, v, r, s) = signed_result(&trivia, 0x42, U256::ZERO, &winners, 7);
+
+        let _ = trivia.resolve_game_signed(U256::ZERO, winners, U256::from(7), v, r, s);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not signed by an oracle")]
+    fn resolve_game_signed_rejects_changed_winners() {
+        let vm = TestVM::default();
+        let (mut trivia, _) = oracle_games(&vm);
+        let (_, v, r, s) = signed_result(&trivia, ORACLE_KEY, U256::ZERO, &players(1), 7);
+
+        let _ = trivia.resolve_game_signed(U256::ZERO, vec![Address::repeat_byte(0x31)], U256::from(7), v, r, s);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn resolve_game_signed_rejects_a_bad_signature() {
+        let vm = TestVM::default();
+        let (mut trivia, _) = oracle_games(&vm);
+
+        let _ = trivia.resolve_game_signed(U256::ZERO, players(1), U256::from(7), 27, B256::ZERO, B256::ZERO);
+    }
+
+    #[test]
+    #[should_panic(expected = "Result nonce already used")]
+    fn resolve_game_signed_rejects_a_reused_nonce() {
+        let vm = TestVM::default();
+        let (mut trivia, _) = oracle_games(&vm);
+        let winners = players(1);
+        let (_, v, r, s) = signed_result(&trivia, ORACLE_KEY, U256::ZERO, &winners, 7);
+        assert!(trivia.resolve_game_signed(U256::ZERO, winners.clone(), U256::from(7), v, r, s).is_ok());
+
+        // a fresh signature for the other game does not make the nonce usable again
+        let (_, v, r, s) = signed_result(&trivia, ORACLE_KEY, U256::from(1), &winners, 7);
+        let _ = trivia.resolve_game_signed(U256::from(1), winners, U256::from(7), v, r, s);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not signed by an oracle")]
+    fn resolve_game_signed_rejects_a_rotated_out_key() {
+        let vm = TestVM::default();
+        let (mut trivia, _) = oracle_games(&vm);
+        let winners = players(1);
+        let (oracle, v, r, s) = signed_result(&trivia, ORACLE_KEY, U256::ZERO, &winners, 7);
+        let (new_oracle, ..) = hostio_stubs::sign(0x42, B256::ZERO);
+        vm.set_sender(ADMIN);
+        trivia.rotate_oracle(oracle, new_oracle);
+        assert!(trivia.is_oracle(new_oracle) && !trivia.is_oracle(oracle));
+
+        let _ = trivia.resolve_game_signed(U256::ZERO, winners, U256::from(7), v, r, s);
     }
 }
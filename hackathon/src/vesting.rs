@@ -0,0 +1,42 @@
+//! Vesting grants for [`EmployerPool`](crate::EmployerPool).
+//!
+//! A grant locks part of an employer's pool balance for a worker. Nothing
+//! vests before the cliff, after which the grant releases linearly over its
+//! duration, or in whole `step` sized increments when a step is set.
+//! Revoking a grant shrinks `total` to what had vested at that moment.
+
+use stylus_sdk::alloy_primitives::U256;
+
+use crate::Grant;
+
+impl Grant {
+    /// Amount vested at `now`, claimed or not
+    pub fn vested_at(&self, now: u64) -> U256 {
+        let total = self.total.get();
+        if self.revoked.get() {
+            return total;
+        }
+
+        let start = self.start.get().to::<u64>();
+        let cliff = self.cliff.get().to::<u64>();
+        let duration = self.duration.get().to::<u64>();
+        if now < start + cliff {
+            return U256::ZERO;
+        }
+        if now >= start + duration {
+            return total;
+        }
+
+        let mut elapsed = now - start;
+        let step = self.step.get().to::<u64>();
+        if step > 0 {
+            elapsed -= elapsed % step;
+        }
+        total * U256::from(elapsed) / U256::from(duration)
+    }
+
+    /// What the worker can claim at `now`
+    pub fn claimable_at(&self, now: u64) -> U256 {
+        self.vested_at(now) - self.claimed.get()
+    }
+}
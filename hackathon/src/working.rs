@@ -1,14 +1,19 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 extern crate alloc;
 
+mod admin;
+mod guardian;
+mod reentrancy;
+mod safe_erc20;
+
+use crate::admin::{Admin, AdminError};
+use crate::guardian::{GuardianError, Guardians};
+use crate::reentrancy::ReentrancyGuard;
+use crate::safe_erc20::TokenError;
 use stylus_sdk::{
-    alloy_primitives::{Address, U256},
-    alloy_sol_types::{sol, SolCall},
-    call,
-    call::RawCall,
-    contract,
+    alloy_primitives::{Address, U256, U64},
+    alloy_sol_types::sol,
     prelude::*,
-    storage::StorageAddress,
 };
 
 
@@ -16,44 +21,53 @@ sol_storage! {
     #[entrypoint]
     pub struct EmployerPool {
         address token;
-        address admin;
+        Admin admin;
         mapping(address => uint256) balances;
+        Guardians guardians;
+        ReentrancyGuard reentrancy;
+        uint64 withdrawal_notice;
+        mapping(address => WithdrawalRequest) withdrawal_requests;
+        // amount the pool should hold, deposits received minus transfers out
+        uint256 token_holdings;
     }
-}
 
-sol_interface! {
-    interface IERC20 {
-    function balanceOf(address account) external view returns (uint256);
-    function transfer(address recipient, uint256 amount)
-        external
-        returns (bool);
-    function transferFrom(address sender, address recipient, uint256 amount)
-        external
-        returns (bool);
+    pub struct WithdrawalRequest {
+        uint256 amount;
+        // 0 when nothing is requested
+        uint64 eta;
     }
 }
 
-pub struct Worker {
-    worker_address: Address,
-    amount_paid: U256,
-}
-
 sol! {
-
-    function balanceOf(address account) returns (uint256);
-    function transfer(address recipient, uint256 value) returns (bool);
     event Transfer(address indexed from, address indexed to, uint256 value);
+    event AdminChanged(address indexed previous_admin, address indexed new_admin);
+    event AdminChangeProposed(address indexed current_admin, address indexed pending_admin, uint64 eta);
+    event AdminChangeCancelled(address indexed pending_admin);
+    event AdminDelayChanged(uint64 delay, uint64 eta);
+    event GuardiansChangeProposed(address[] guardians, uint256 threshold, uint64 eta);
+    event GuardiansChangeCancelled();
+    event GuardiansChanged(address[] guardians, uint256 threshold);
+    event WithdrawalProposed(uint256 indexed proposal_id, address indexed employer, address token, uint256 amount, address destination, uint64 expiry);
+    event WithdrawalApproved(uint256 indexed proposal_id, address indexed guardian, uint256 approvals);
+    event WithdrawalCancelled(uint256 indexed proposal_id);
+    event EmergencyWithdrawal(address indexed employer, address indexed to, address token, uint256 amount);
+    event WithdrawalNoticeChanged(uint64 notice);
+    event WithdrawalRequested(address indexed employer, address indexed token, uint256 amount, uint64 eta);
+    event WithdrawalRequestCancelled(address indexed employer, address indexed token);
+    event EmployerWithdrawal(address indexed employer, address indexed token, uint256 amount);
+    event TokenHoldingsSynced(uint256 tracked, uint256 actual);
 }
 
 #[public]
 impl EmployerPool {
     /// (Constructor) intialize the admin
     pub fn initialize_admin(&mut self) {
-        if Address::from(*self.admin.get()) == Address::default() {
-            self.admin.set(self.vm().msg_sender());
-        }
+        self.reentrancy.check();
+        let admin = self.vm().msg_sender();
+        self.admin.initialize(admin);
     }
     pub fn set_address(&mut self, _token: Address) {
+        self.reentrancy.check();
         assert_eq!(
             Address::from(*self.admin.get()),
             self.vm().msg_sender(),
@@ -62,68 +76,199 @@ impl EmployerPool {
         self.token.set(_token);
     }
 
-    
+    //proposes new_admin, who has to call accept_admin once admin_delay has passed
     pub fn change_admin(&mut self, new_admin: Address) {
-        assert_eq!(
-            Address::from(*self.admin.get()),
-            self.vm().msg_sender(),
-            "Only admin can change admin"
-        );
-        self.admin.set(new_admin);
+        self.reentrancy.check();
+        let eta = self.admin.propose(new_admin);
+        log(self.vm(), AdminChangeProposed { current_admin: self.admin.get(), pending_admin: new_admin, eta });
+    }
+
+    pub fn accept_admin(&mut self) -> Result<(), AdminError> {
+        self.reentrancy.check();
+        let previous_admin = self.admin.accept()?;
+        log(self.vm(), AdminChanged { previous_admin, new_admin: self.admin.get() });
+        Ok(())
+    }
+
+    pub fn cancel_admin_change(&mut self) {
+        self.reentrancy.check();
+        let pending_admin = self.admin.cancel();
+        log(self.vm(), AdminChangeCancelled { pending_admin });
+    }
+
+    //takes effect once the current delay has passed, then applies to admin changes proposed afterwards
+    pub fn set_admin_delay(&mut self, delay: u64) {
+        self.reentrancy.check();
+        let eta = self.admin.set_delay(delay);
+        log(self.vm(), AdminDelayChanged { delay, eta });
+    }
+
+    //(pending admin, time from which it can accept)
+    pub fn pending_admin(&self) -> (Address, u64) {
+        self.admin.pending()
+    }
+
+    pub fn admin_delay(&self) -> u64 {
+        self.admin.delay()
+    }
+
+    //(delay, time it takes effect), eta 0 when no change is scheduled
+    pub fn pending_admin_delay(&self) -> (u64, u64) {
+        self.admin.pending_delay()
     }
 
     //deposits after intialized address
 
-    pub fn deposit(&mut self, amount: U256) -> bool {
+    //credits what actually arrived, so fee on transfer tokens are credited net of the fee
+    //rejects rebasing tokens, the pool balance must never drop below what it accounted for
+    pub fn deposit(&mut self, amount: U256) -> Result<bool, Vec<u8>> {
+        self.reentrancy.check();
         let employer = self.vm().msg_sender();
-        let current = self.balances.get(employer);
+        let pool = self.vm().contract_address();
 
         // Check the external token balance of the employer.
-        let ext_balance = self.token_balance(employer);
+        let ext_balance = self.token_balance(employer)?;
         if ext_balance < amount {
-            return false;
+            return Ok(false);
         }
 
-        let success = Self::transfer_from_token(self, employer, contract::address(), amount);
-        
-        self.balances.setter(employer).set(current + amount);
-        success
+        let tracked = self.token_holdings.get();
+        let before = self.token_balance(pool)?;
+        assert!(before >= tracked, "Pool token balance dropped, see sync_token_holdings");
+
+        // the credit depends on the measured delta so it comes after the call,
+        // the reentrancy lock held during transfer_from_token covers it
+        Self::transfer_from_token(self, employer, pool, amount)?;
+        let received = self.token_balance(pool)?.saturating_sub(before);
+        assert!(received <= amount, "Received more than was sent, rebasing tokens are not supported");
+
+        let current = self.balances.get(employer);
+        self.balances.setter(employer).set(current + received);
+        self.token_holdings.set(tracked + received);
+        Ok(true)
     }
 
-    pub fn pay_workers(&mut self, workers: Vec<(Address, U256)>) -> bool {
+    //after the token rebased down deposits revert, this lowers what the pool expects to hold
+    //to what it actually holds so they work again, employer balances are left as they are
+    pub fn sync_token_holdings(&mut self) -> Result<U256, TokenError> {
+        self.reentrancy.check();
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can mutate this"
+        );
+        let tracked = self.token_holdings.get();
+        let actual = self.balance()?;
+        if actual >= tracked {
+            return Ok(tracked);
+        }
+        self.token_holdings.set(actual);
+        log(self.vm(), TokenHoldingsSynced { tracked, actual });
+        Ok(actual)
+    }
+
+    pub fn pay_workers(&mut self, workers: Vec<(Address, U256)>) -> Result<bool, Vec<u8>> {
+        self.reentrancy.check();
         let employer = self.vm().msg_sender();
-        let mut bal = Self::employer_balance(&self, employer);
+        let mut bal = Self::employer_balance(self, employer);
 
         
         for (worker_address, amount) in workers {
+            assert_ne!(worker_address, employer, "Use request_withdrawal to take funds back");
             if bal < amount {
                 continue;
             }
-            let success = Self::transfer_token(self, worker_address, amount);
-            bal = bal - amount;
+            bal -= amount;
             self.balances.setter(employer).set(bal);
+            Self::transfer_token(self, worker_address, amount)?;
         }
-        true
+        Ok(true)
     }
     //employer address will be passed based on the amount they have in the pool
     //admin only admin can call
-    pub fn auto_pay_workers(&mut self, employer:Address ,workers: Vec<(Address, U256)>) -> bool {
+    pub fn auto_pay_workers(&mut self, employer:Address ,workers: Vec<(Address, U256)>) -> Result<bool, Vec<u8>> {
+        self.reentrancy.check();
         assert_eq!(
             Address::from(*self.admin.get()),
             employer,
             "Only admin can auto pay workers!"
         );
-        let mut bal = Self::employer_balance(&self, employer);
+        let mut bal = Self::employer_balance(self, employer);
 
         for (worker_address, amount) in workers {
+            assert_ne!(worker_address, employer, "Use request_withdrawal to take funds back");
             if bal < amount {
                 continue;
             }
-            let success = Self::transfer_token(self, worker_address, amount);
-            bal = bal - amount;
+            bal -= amount;
             self.balances.setter(employer).set(bal);
+            Self::transfer_token(self, worker_address, amount)?;
         }
-        true
+        Ok(true)
+    }
+
+    //employers take unused funds back after a notice period, so workers see it coming
+    pub fn set_withdrawal_notice(&mut self, notice: u64) {
+        self.reentrancy.check();
+        assert_eq!(
+            Address::from(*self.admin.get()),
+            self.vm().msg_sender(),
+            "Only admin can mutate this"
+        );
+        self.withdrawal_notice.set(U64::from(notice));
+        log(self.vm(), WithdrawalNoticeChanged { notice });
+    }
+
+    pub fn withdrawal_notice(&self) -> u64 {
+        self.withdrawal_notice.get().to::<u64>()
+    }
+
+    //replaces any earlier request, returns when it can be executed
+    pub fn request_withdrawal(&mut self, amount: U256) -> u64 {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        assert!(self.balances.get(employer) >= amount, "Employer balance too low");
+
+        let eta = self.vm().block_timestamp() + self.withdrawal_notice.get().to::<u64>();
+        let mut request = self.withdrawal_requests.setter(employer);
+        request.amount.set(amount);
+        request.eta.set(U64::from(eta));
+        log(self.vm(), WithdrawalRequested { employer, token: self.token.get(), amount, eta });
+        eta
+    }
+
+    pub fn cancel_withdrawal(&mut self) {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let mut request = self.withdrawal_requests.setter(employer);
+        assert!(request.eta.get() != U64::ZERO, "No withdrawal requested");
+        request.amount.set(U256::ZERO);
+        request.eta.set(U64::ZERO);
+        log(self.vm(), WithdrawalRequestCancelled { employer, token: self.token.get() });
+    }
+
+    pub fn execute_withdrawal(&mut self) -> Result<U256, Vec<u8>> {
+        self.reentrancy.check();
+        let employer = self.vm().msg_sender();
+        let (amount, eta) = self.withdrawal_request(employer);
+        assert!(eta != 0, "No withdrawal requested");
+        assert!(self.vm().block_timestamp() >= eta, "Withdrawal notice has not passed");
+        let bal = self.balances.get(employer);
+        assert!(bal >= amount, "Employer balance too low");
+
+        let mut request = self.withdrawal_requests.setter(employer);
+        request.amount.set(U256::ZERO);
+        request.eta.set(U64::ZERO);
+        self.balances.setter(employer).set(bal - amount);
+        Self::transfer_token(self, employer, amount)?;
+        log(self.vm(), EmployerWithdrawal { employer, token: self.token.get(), amount });
+        Ok(amount)
+    }
+
+    //(amount, time from which it can be executed), eta 0 when nothing is requested
+    pub fn withdrawal_request(&self, employer: Address) -> (U256, u64) {
+        let request = self.withdrawal_requests.getter(employer);
+        (request.amount.get(), request.eta.get().to::<u64>())
     }
 
     pub fn employer_balance(&self, employer: Address) -> U256 {
@@ -134,33 +279,97 @@ impl EmployerPool {
         self.balances.get(self.vm().msg_sender())
     }
 
-    pub fn token_balance(&self, owner: Address) -> U256 {
-        let result = RawCall::new_static().call(
-            alloy_primitives::Address(*self.token.get()),
-            &balanceOfCall { account: owner }.abi_encode(),
-        );
-
-        match result {
-            Ok(data) => U256::from_be_bytes::<32>(data.try_into().unwrap_or([0u8; 32])),
-            Err(_) => U256::from(0), // Returns 0 if the call fails
-        }
+    //reverts when the token call fails instead of reporting 0
+    pub fn token_balance(&self, owner: Address) -> Result<U256, TokenError> {
+        safe_erc20::balance_of(self.token.get(), owner)
     }
 
-    pub fn emergency_withdraw(&mut self, _amount:U256) {
-        //in case of emergency... most esp lost wallet
-        let admin : Address = self.admin.get();
+    //in case of emergency... most esp lost wallet
+    //withdrawals come out of one employer's balance and need threshold guardian approvals
+    //the new set only takes effect admin_delay, and at least guardian::MIN_SET_DELAY, after it was proposed
+    //see apply_guardians
+    pub fn set_guardians(&mut self, guardians: Vec<Address>, threshold: U256) {
+        self.reentrancy.check();
         assert_eq!(
             Address::from(*self.admin.get()),
             self.vm().msg_sender(),
-            "Only can call this booga ooga restrictive function"
+            "Only admin can mutate this"
+        );
+        let eta = self.guardians.propose_set(guardians.clone(), threshold, self.admin.delay());
+        log(self.vm(), GuardiansChangeProposed { guardians, threshold, eta });
+    }
+
+    //anyone can apply the proposed set once its delay has passed
+    pub fn apply_guardians(&mut self) -> Result<(), GuardianError> {
+        self.reentrancy.check();
+        let (guardians, threshold) = self.guardians.apply_set()?;
+        log(self.vm(), GuardiansChanged { guardians, threshold });
+        Ok(())
+    }
+
+    pub fn cancel_guardians_change(&mut self) {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        assert!(
+            sender == self.admin.get() || self.guardians.is_member(sender),
+            "Only admin or a guardian can cancel"
         );
+        self.guardians.cancel_set();
+        log(self.vm(), GuardiansChangeCancelled {});
+    }
+
+    pub fn propose_emergency_withdraw(
+        &mut self,
+        employer: Address,
+        amount: U256,
+        destination: Address,
+        expiry: u64,
+    ) -> U256 {
+        self.reentrancy.check();
+        assert!(self.balances.get(employer) >= amount, "Employer balance too low");
+        let token = self.token.get();
 
-        Self::transfer_token(self, admin, _amount);
+        let proposal_id = self.guardians.propose(employer, token, amount, destination, expiry);
+        log(self.vm(), WithdrawalProposed { proposal_id, employer, token, amount, destination, expiry });
+        proposal_id
     }
 
-    fn balance(&self) -> U256{
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-        token.balance()
+    pub fn approve_emergency_withdraw(&mut self, proposal_id: U256) -> Result<(), GuardianError> {
+        self.reentrancy.check();
+        let approvals = self.guardians.approve(proposal_id)?;
+        log(self.vm(), WithdrawalApproved { proposal_id, guardian: self.vm().msg_sender(), approvals });
+        Ok(())
+    }
+
+    pub fn cancel_emergency_withdraw(&mut self, proposal_id: U256) -> Result<(), GuardianError> {
+        self.reentrancy.check();
+        let sender = self.vm().msg_sender();
+        assert!(
+            sender == self.admin.get() || self.guardians.is_member(sender),
+            "Only admin or a guardian can cancel"
+        );
+        self.guardians.cancel(proposal_id)?;
+        log(self.vm(), WithdrawalCancelled { proposal_id });
+        Ok(())
+    }
+
+    pub fn execute_emergency_withdraw(&mut self, proposal_id: U256) -> Result<(), Vec<u8>> {
+        self.reentrancy.check();
+        let withdrawal = self.guardians.execute(proposal_id)?;
+        let employer = withdrawal.employer;
+        let amount = withdrawal.amount;
+        let destination = withdrawal.destination;
+
+        let bal = self.balances.get(employer);
+        assert!(bal >= amount, "Employer balance too low");
+        self.balances.setter(employer).set(bal - amount);
+        Self::transfer_token(self, destination, amount)?;
+        log(self.vm(), EmergencyWithdrawal { employer, to: destination, token: withdrawal.token, amount });
+        Ok(())
+    }
+
+    fn balance(&self) -> Result<U256, TokenError>{
+        self.token_balance(self.vm().contract_address())
     }
 }
 
@@ -168,17 +377,15 @@ impl EmployerPool {
 
     //internal func
 
-    fn transfer_from_token(&mut self, from: Address, to: Address, amount: U256) -> bool {
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-
-        token
-            .transfer_from(self, from, to, amount)
-            .expect("approve token first")
+    fn transfer_from_token(&mut self, from: Address, to: Address, amount: U256) -> Result<(), TokenError> {
+        let token = self.token.get();
+        self.reentrancy.transfer_from(token, from, to, amount)
     }
-    fn transfer_token(&mut self, to: Address, amount: U256) -> bool {
-        let token: ERC20 = ERC20::new(alloy_primitives::Address(*self.token.get()));
-        token
-            .transfer(self, to, amount)
-            .expect("approve token first")
+    fn transfer_token(&mut self, to: Address, amount: U256) -> Result<(), TokenError> {
+        let token = self.token.get();
+        self.reentrancy.transfer(token, to, amount)?;
+        let held = self.token_holdings.get();
+        self.token_holdings.set(held.saturating_sub(amount));
+        Ok(())
     } 
 }